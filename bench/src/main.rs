@@ -46,7 +46,7 @@ fn via_ya_redis<R: Rng>(server: &str, rng: &mut R, con: &mut Connection, n: usiz
     for i in 0..N_KEYS {
         let a = (0..n).map(|_| A::gen(rng)).collect::<Vec<_>>();
         let key = format!("{}", i);
-        let _: bool = con.set(&key, &V(a)).unwrap();
+        let _: bool = con.set(&key, V(a)).unwrap();
         let mut scores = 0;
         for _ in 0..N_READ_HEAVY {
             if let Some(v) = con.get::<_, Option<V>>(&key).unwrap() {
@@ -85,7 +85,7 @@ fn via_serde_json<R: Rng>(
         let a = (0..n).map(|_| A::gen(rng)).collect::<Vec<_>>();
         let key = format!("{}", i);
         let s = serde_json::to_string(&a).unwrap();
-        bytes += s.as_bytes().len();
+        bytes += s.len();
         let _: bool = con.set(&key, s).unwrap();
         let mut scores = 0;
         for _ in 0..N_READ_HEAVY {
@@ -0,0 +1,137 @@
+//! Byte formats of stored values. See [`Codec`].
+use std::fmt::Display;
+
+use redis::{ConnectionLike, ErrorKind, RedisError, RedisResult, ToRedisArgs};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Error, Options};
+
+/// Format values are stored in.
+///
+/// `#[derive(Redis)]` encodes with [`Options`] built from the container attributes,
+/// or with the codec given by `#[redis(codec = path::to::CODEC)]`.
+pub trait Codec {
+    type Error: std::fmt::Display;
+
+    fn encode<T: ?Sized + Serialize>(&self, value: &T) -> Result<Vec<u8>, Self::Error>;
+
+    fn decode<T: DeserializeOwned>(&self, b: &[u8]) -> Result<T, Self::Error>;
+}
+
+/// The ya binary format.
+impl Codec for Options {
+    type Error = Error;
+
+    fn encode<T: ?Sized + Serialize>(&self, value: &T) -> Result<Vec<u8>, Error> {
+        self.try_to_bytes(value)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, b: &[u8]) -> Result<T, Error> {
+        self.from_bytes(b)
+    }
+}
+
+/// JSON, for values meant to be read by people or other tools. Requires the `json` feature.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    type Error = serde_json::Error;
+
+    fn encode<T: ?Sized + Serialize>(&self, value: &T) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(value)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, b: &[u8]) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(b)
+    }
+}
+
+/// Decode `b` with `primary` or, failing that, with `fallback`, the format being migrated from.
+///
+/// `primary` is tried first, so values written since the switch are never misread, while a legacy
+/// value which happens to decode in `primary` as well is read as such: `#[redis(fallback)]`
+/// requires `checksum`, so that only a matching CRC32C lets one through. Returns whether `fallback` decoded it, and the error of `primary` if neither could.
+pub fn decode_with_fallback<T, P, F>(
+    primary: &P,
+    fallback: &F,
+    b: &[u8],
+) -> Result<(T, bool), P::Error>
+where
+    T: DeserializeOwned,
+    P: Codec,
+    F: Codec,
+{
+    match primary.decode(b) {
+        Ok(v) => Ok((v, false)),
+        Err(e) => fallback.decode(b).map(|v| (v, true)).map_err(|_| e),
+    }
+}
+
+/// Writes `ARGV[2]` if the key still holds `ARGV[1]`, keeping its TTL. `KEEPTTL` needs Redis 6.0.
+const COMPARE_AND_SET: &str = "\
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('SET', KEYS[1], ARGV[2], 'KEEPTTL')
+end
+return false";
+
+/// `GET` the value at `key` like [`decode_with_fallback`], and if it was in the `fallback` format,
+/// write it back in the `primary` one.
+///
+/// The value is only replaced if it did not change in between, and keeps its TTL.
+pub fn get_migrating<T, P, F, C, K>(
+    con: &mut C,
+    key: K,
+    primary: &P,
+    fallback: &F,
+) -> RedisResult<Option<T>>
+where
+    T: Serialize + DeserializeOwned,
+    P: Codec,
+    F: Codec,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    let b: Option<Vec<u8>> = redis::cmd("GET").arg(&key).query(con)?;
+    let b = match b {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+    let (v, legacy) = decode_with_fallback(primary, fallback, &b).map_err(decode_error)?;
+    if legacy {
+        let migrated = primary.encode(&v).map_err(|e| {
+            RedisError::from((
+                ErrorKind::TypeError,
+                "failed to encode the value to migrate",
+                e.to_string(),
+            ))
+        })?;
+        redis::cmd("EVAL")
+            .arg(COMPARE_AND_SET)
+            .arg(1)
+            .arg(&key)
+            .arg(&b)
+            .arg(migrated)
+            .query::<redis::Value>(con)?;
+    }
+    Ok(Some(v))
+}
+
+pub(crate) fn decode_error(e: impl Display) -> RedisError {
+    RedisError::from((
+        ErrorKind::TypeError,
+        "failed to decode the data got from redis",
+        e.to_string(),
+    ))
+}
+
+/// Encode for `ToRedisArgs`, which cannot fail.
+#[doc(hidden)]
+pub fn encode_or_panic<C: Codec, T: ?Sized + Serialize>(codec: &C, value: &T) -> Vec<u8> {
+    match codec.encode(value) {
+        Ok(b) => b,
+        Err(e) => panic!("failed to encode {}: {}", std::any::type_name::<T>(), e),
+    }
+}
@@ -0,0 +1,58 @@
+//! Types whose encoding always takes the same number of bytes. See [`FixedSize`].
+
+/// Encoded size of a type, when it is the same for every value.
+///
+/// `#[redis(fixed_layout)]` requires it of every field and implements it for the struct,
+/// so fixed layout structs can be nested.
+pub trait FixedSize {
+    /// Number of bytes a value takes when encoded without options.
+    const SIZE: usize;
+}
+
+macro_rules! impl_fixed_size {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl FixedSize for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+            }
+        )*
+    };
+}
+
+impl_fixed_size!(
+    bool,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    f32,
+    f64,
+    ()
+);
+
+impl<T: FixedSize, const N: usize> FixedSize for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+}
+
+macro_rules! impl_tuple {
+    ($($t:ident)+) => {
+        impl<$($t: FixedSize),+> FixedSize for ($($t,)+) {
+            const SIZE: usize = 0 $(+ $t::SIZE)+;
+        }
+    };
+}
+
+impl_tuple!(T0);
+impl_tuple!(T0 T1);
+impl_tuple!(T0 T1 T2);
+impl_tuple!(T0 T1 T2 T3);
+impl_tuple!(T0 T1 T2 T3 T4);
+impl_tuple!(T0 T1 T2 T3 T4 T5);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7);
@@ -0,0 +1,509 @@
+//! Structs stored as Redis hashes, one hash field per struct field. See `#[redis(hash)]`.
+//!
+//! Numbers, `bool`s (as `1` or `0`), `char`s and strings are stored as text, so that commands like
+//! `HINCRBY` work on them and other clients can read them, and bytes as they are.
+//! Other values are encoded with the options of the struct. `None` is stored as no field at all,
+//! so a bare `HSET` of a struct leaves the fields of a previous value which it does not overwrite;
+//! use it only on a fresh key, and [`save`] to replace a hash.
+//!
+//! Fields with `#[redis(flatten)]` are instead spread over a field per scalar, with dotted names
+//! like `address.city`, `scores.0` or `shape.Circle.radius`: struct fields, sequence and tuple
+//! items, map entries and enum variants each add a segment. Unit variants are stored as their name
+//! and units as an empty value. Since `None` is stored as no field, `None` items at the end of
+//! a sequence are lost, and the length of a sequence is read from its last index, up to
+//! [`MAX_LEN`]. Empty sequences and maps have no field either, so missing struct fields are read as
+//! those or `None`, never with `#[serde(default)]`. Map keys must be scalars; `.` and `\` in them
+//! and in the other segments are escaped with `\`. Fields of a previous value, like items past
+//! the end of a shorter sequence or those of another variant, are read back unless the whole hash
+//! is replaced with [`save`].
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use redis::{
+    ConnectionLike, ErrorKind, FromRedisValue, RedisError, RedisResult, RedisWrite, ToRedisArgs,
+};
+use serde::{
+    de::{self, DeserializeOwned, Visitor},
+    ser::{self, Impossible},
+    Deserializer, Serialize, Serializer,
+};
+
+use crate::{Error, Options};
+
+mod flatten;
+
+/// Most items of a flattened sequence, which bounds what a corrupt index allocates.
+pub const MAX_LEN: usize = 1 << 20;
+
+/// Hash field value of `value`, or `None` if it is `None` and stored as no field.
+pub fn encode_field<T: ?Sized + Serialize>(options: &Options, value: &T) -> Option<Vec<u8>> {
+    match value.serialize(TextSerializer) {
+        Ok(text) => text,
+        Err(_) => Some(options.to_bytes(value)),
+    }
+}
+
+/// Decode a hash field value written by [`encode_field`], or a missing field if `b` is `None`.
+pub fn decode_field<T: DeserializeOwned>(options: &Options, b: Option<&[u8]>) -> Result<T, Error> {
+    match b {
+        Some(b) => match T::deserialize(TextDeserializer(b)) {
+            Err(TextError::NotText) => options.from_bytes(b),
+            r => r.map_err(Error::from),
+        },
+        None => T::deserialize(Missing).map_err(Error::from),
+    }
+}
+
+/// Write the field name and value for `HSET`, unless the value is `None`.
+pub fn write_field<W, T>(out: &mut W, options: &Options, name: &str, value: &T)
+where
+    W: ?Sized + RedisWrite,
+    T: ?Sized + Serialize,
+{
+    if let Some(b) = encode_field(options, value) {
+        out.write_arg(name.as_bytes());
+        out.write_arg(&b);
+    }
+}
+
+/// Decode the field `name` out of the fields returned by `HGETALL`.
+pub fn read_field<T: DeserializeOwned>(
+    fields: &HashMap<Vec<u8>, Vec<u8>>,
+    options: &Options,
+    name: &str,
+) -> RedisResult<T> {
+    decode_field(options, fields.get(name.as_bytes()).map(Vec::as_slice))
+        .map_err(|e| field_error(name, e))
+}
+
+fn field_error(name: &str, e: Error) -> RedisError {
+    RedisError::from((
+        ErrorKind::TypeError,
+        "failed to decode the hash field got from redis",
+        format!("{}: {}", name, e),
+    ))
+}
+
+/// Read the field `name` of the hash at `key` with `HGET`.
+pub fn get_field<T, C, K>(con: &mut C, key: K, options: &Options, name: &str) -> RedisResult<T>
+where
+    T: DeserializeOwned,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    let b: Option<Vec<u8>> = redis::cmd("HGET").arg(key).arg(name).query(con)?;
+    decode_field(options, b.as_deref()).map_err(|e| field_error(name, e))
+}
+
+/// Write the field `name` of the hash at `key` with `HSET`, or delete it with `HDEL` if `value`
+/// is `None`.
+pub fn set_field<T, C, K>(
+    con: &mut C,
+    key: K,
+    options: &Options,
+    name: &str,
+    value: &T,
+) -> RedisResult<()>
+where
+    T: ?Sized + Serialize,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    match encode_field(options, value) {
+        Some(b) => redis::cmd("HSET").arg(key).arg(name).arg(b).query(con),
+        None => redis::cmd("HDEL").arg(key).arg(name).query(con),
+    }
+}
+
+/// Replace the hash at `key` with the fields `value` writes for `HSET`.
+///
+/// This runs `DEL` and `HSET` in a `MULTI`, so that no field of the previous value is left,
+/// like one which is now `None`. The key loses its TTL.
+pub fn save<T, C, K>(con: &mut C, key: K, value: &T) -> RedisResult<()>
+where
+    T: ToRedisArgs,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    let fields = value.to_redis_args();
+    let mut pipe = redis::pipe();
+    pipe.atomic().cmd("DEL").arg(&key).ignore();
+    // `HSET` takes at least one field, and a hash without any is no hash at all
+    if !fields.is_empty() {
+        pipe.cmd("HSET").arg(&key).arg(fields).ignore();
+    }
+    pipe.query(con)
+}
+
+/// Add `delta` to the integer field `name` of the hash at `key` with `HINCRBY`,
+/// and return the new value. A missing field counts as 0.
+pub fn incr_field<T, C, K>(con: &mut C, key: K, name: &str, delta: T) -> RedisResult<T>
+where
+    T: ToRedisArgs + FromRedisValue,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    redis::cmd("HINCRBY")
+        .arg(key)
+        .arg(name)
+        .arg(delta)
+        .query(con)
+}
+
+/// Like [`incr_field`] for a float field, with `HINCRBYFLOAT`.
+pub fn incr_float_field<T, C, K>(con: &mut C, key: K, name: &str, delta: T) -> RedisResult<T>
+where
+    T: ToRedisArgs + FromRedisValue,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    redis::cmd("HINCRBYFLOAT")
+        .arg(key)
+        .arg(name)
+        .arg(delta)
+        .query(con)
+}
+
+/// Write the hash fields of `value` flattened under `name` for `HSET`.
+///
+/// # Panics
+///
+/// If a map key is not a scalar, since `ToRedisArgs` cannot fail.
+pub fn write_flattened<W, T>(out: &mut W, name: &str, value: &T)
+where
+    W: ?Sized + RedisWrite,
+    T: ?Sized + Serialize,
+{
+    let mut fields = Vec::new();
+    let serializer = flatten::FlatSerializer {
+        prefix: name.to_owned(),
+        out: &mut fields,
+    };
+    if let Err(e) = value.serialize(serializer) {
+        panic!("failed to flatten `{}`: {}", name, e);
+    }
+    for (name, b) in fields {
+        out.write_arg(name.as_bytes());
+        out.write_arg(&b);
+    }
+}
+
+/// Decode the value flattened under `name` out of the fields returned by `HGETALL`.
+pub fn read_flattened<T: DeserializeOwned>(
+    fields: &HashMap<Vec<u8>, Vec<u8>>,
+    name: &str,
+) -> RedisResult<T> {
+    let deserializer = flatten::FlatDeserializer {
+        fields,
+        prefix: name.to_owned(),
+    };
+    T::deserialize(deserializer).map_err(|e| {
+        RedisError::from((
+            ErrorKind::TypeError,
+            "failed to decode the hash fields got from redis",
+            format!("{}: {}", name, e),
+        ))
+    })
+}
+
+#[derive(Debug)]
+enum TextError {
+    /// The value is not stored as text.
+    NotText,
+    Custom(String),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::NotText => f.write_str("the value is not stored as text"),
+            TextError::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl ser::Error for TextError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        TextError::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for TextError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        TextError::Custom(msg.to_string())
+    }
+}
+
+impl From<TextError> for Error {
+    fn from(e: TextError) -> Error {
+        Error::Custom(e.to_string())
+    }
+}
+
+/// Text of scalar values, `None` for `None`, and [`TextError::NotText`] for the others.
+struct TextSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty),)*) => {
+        $(fn $method(self, v: $ty) -> Result<Self::Ok, TextError> {
+            Ok(Some(v.to_string().into_bytes()))
+        })*
+    };
+}
+
+impl Serializer for TextSerializer {
+    type Ok = Option<Vec<u8>>;
+    type Error = TextError;
+    type SerializeSeq = Impossible<Self::Ok, TextError>;
+    type SerializeTuple = Impossible<Self::Ok, TextError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, TextError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, TextError>;
+    type SerializeMap = Impossible<Self::Ok, TextError>;
+    type SerializeStruct = Impossible<Self::Ok, TextError>;
+    type SerializeStructVariant = Impossible<Self::Ok, TextError>;
+
+    serialize_display! {
+        serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64),
+        serialize_i128(i128), serialize_u8(u8), serialize_u16(u16), serialize_u32(u32),
+        serialize_u64(u64), serialize_u128(u128), serialize_f32(f32), serialize_f64(f64),
+        serialize_char(char), serialize_str(&str),
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, TextError> {
+        Ok(Some(vec![if v { b'1' } else { b'0' }]))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, TextError> {
+        Ok(Some(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, TextError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, TextError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, TextError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, TextError> {
+        Err(TextError::NotText)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, TextError> {
+        Err(TextError::NotText)
+    }
+}
+
+/// Reads what [`TextSerializer`] writes, failing with [`TextError::NotText`] for other values.
+struct TextDeserializer<'de>(&'de [u8]);
+
+impl<'de> TextDeserializer<'de> {
+    fn text(&self) -> Result<&'de str, TextError> {
+        std::str::from_utf8(self.0).map_err(|_| TextError::Custom("invalid UTF-8".into()))
+    }
+
+    fn parse<T: FromStr>(&self) -> Result<T, TextError> {
+        let text = self.text()?;
+        text.parse()
+            .map_err(|_| TextError::Custom(format!("invalid number: {:?}", text)))
+    }
+}
+
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+            visitor.$visit(self.parse()?)
+        })*
+    };
+}
+
+macro_rules! deserialize_not_text {
+    ($($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* _visitor: V) -> Result<V::Value, TextError> {
+            Err(TextError::NotText)
+        })*
+    };
+}
+
+impl<'de> Deserializer<'de> for TextDeserializer<'de> {
+    type Error = TextError;
+
+    deserialize_parse! {
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64, deserialize_i128 => visit_i128, deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16, deserialize_u32 => visit_u32, deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128, deserialize_f32 => visit_f32, deserialize_f64 => visit_f64,
+    }
+
+    deserialize_not_text! {
+        deserialize_unit(),
+        deserialize_unit_struct(_name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(_len: usize),
+        deserialize_tuple_struct(_name: &'static str, _len: usize),
+        deserialize_map(),
+        deserialize_struct(_name: &'static str, _fields: &'static [&'static str]),
+        deserialize_enum(_name: &'static str, _variants: &'static [&'static str]),
+    }
+
+    /// Text if it is UTF-8, else bytes.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        match std::str::from_utf8(self.0) {
+            Ok(text) => visitor.visit_borrowed_str(text),
+            Err(_) => visitor.visit_borrowed_bytes(self.0),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        match self.0 {
+            b"1" | b"true" => visitor.visit_bool(true),
+            b"0" | b"false" => visitor.visit_bool(false),
+            _ => Err(TextError::Custom(format!(
+                "invalid bool: {:?}",
+                String::from_utf8_lossy(self.0)
+            ))),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        let mut chars = self.text()?.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(TextError::Custom("expected a single character".into())),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_borrowed_str(self.text()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_borrowed_bytes(self.0)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// Fields which are there are never `None`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_unit()
+    }
+}
+
+/// A missing field, which is only valid for `Option`s.
+struct Missing;
+
+impl<'de> Deserializer<'de> for Missing {
+    type Error = TextError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, TextError> {
+        Err(TextError::Custom("missing field".into()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_none()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
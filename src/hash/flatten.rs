@@ -0,0 +1,662 @@
+//! Serializer and deserializer of flattened hash fields. See the [`super`] docs for the names.
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+};
+
+use serde::{
+    de::{
+        DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+    },
+    ser::{self, Serialize, Serializer},
+    Deserializer,
+};
+
+use super::{TextDeserializer, TextError, TextSerializer, MAX_LEN};
+
+/// `segment` with `.` and `\` escaped by `\`, so that it can be told apart from the next one.
+fn escape(segment: &str) -> Cow<'_, str> {
+    if !segment.contains(['.', '\\']) {
+        return Cow::Borrowed(segment);
+    }
+    let mut escaped = String::with_capacity(segment.len() + 1);
+    for c in segment.chars() {
+        if c == '.' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// The first segment of `rest`, up to an unescaped `.`, unescaped.
+fn first_segment(rest: &str) -> Cow<'_, str> {
+    let mut end = rest.len();
+    let mut escaped = false;
+    let mut bytes = rest.bytes().enumerate();
+    while let Some((i, b)) = bytes.next() {
+        match b {
+            b'.' => {
+                end = i;
+                break;
+            }
+            b'\\' => {
+                escaped = true;
+                bytes.next();
+            }
+            _ => {}
+        }
+    }
+    let segment = &rest[..end];
+    if !escaped {
+        return Cow::Borrowed(segment);
+    }
+    let mut unescaped = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Deserialize a segment, as text unless it had escapes, which numbers never do.
+fn deserialize_segment<'de, T: DeserializeSeed<'de>>(
+    seed: T,
+    segment: Cow<'de, str>,
+) -> Result<T::Value, TextError> {
+    match segment {
+        Cow::Borrowed(segment) => seed.deserialize(TextDeserializer(segment.as_bytes())),
+        Cow::Owned(segment) => seed.deserialize(segment.into_deserializer()),
+    }
+}
+
+/// Collects the hash fields of a value under `prefix`.
+pub(super) struct FlatSerializer<'a> {
+    pub(super) prefix: String,
+    pub(super) out: &'a mut Vec<(String, Vec<u8>)>,
+}
+
+impl<'a> FlatSerializer<'a> {
+    fn leaf(self, text: Option<Vec<u8>>) -> Result<(), TextError> {
+        if let Some(text) = text {
+            self.out.push((self.prefix, text));
+        }
+        Ok(())
+    }
+
+    fn child(&mut self, segment: &str) -> FlatSerializer<'_> {
+        FlatSerializer {
+            prefix: format!("{}.{}", self.prefix, escape(segment)),
+            out: self.out,
+        }
+    }
+}
+
+macro_rules! serialize_text {
+    ($($method:ident($ty:ty),)*) => {
+        $(fn $method(self, v: $ty) -> Result<(), TextError> {
+            let text = TextSerializer.$method(v)?;
+            self.leaf(text)
+        })*
+    };
+}
+
+impl<'a> Serializer for FlatSerializer<'a> {
+    type Ok = ();
+    type Error = TextError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    serialize_text! {
+        serialize_bool(bool), serialize_i8(i8), serialize_i16(i16), serialize_i32(i32),
+        serialize_i64(i64), serialize_i128(i128), serialize_u8(u8), serialize_u16(u16),
+        serialize_u32(u32), serialize_u64(u64), serialize_u128(u128), serialize_f32(f32),
+        serialize_f64(f64), serialize_char(char), serialize_str(&str), serialize_bytes(&[u8]),
+    }
+
+    fn serialize_none(self) -> Result<(), TextError> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), TextError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), TextError> {
+        self.leaf(Some(Vec::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), TextError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), TextError> {
+        self.leaf(Some(variant.as_bytes().to_vec()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), TextError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        mut self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), TextError> {
+        value.serialize(self.child(variant))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, TextError> {
+        Ok(Compound::new(self))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>, TextError> {
+        Ok(Compound::new(self))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, TextError> {
+        Ok(Compound::new(self))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, TextError> {
+        Ok(Compound::new(FlatSerializer {
+            prefix: format!("{}.{}", self.prefix, escape(variant)),
+            out: self.out,
+        }))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, TextError> {
+        Ok(Compound::new(self))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>, TextError> {
+        Ok(Compound::new(self))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, TextError> {
+        self.serialize_tuple_variant(name, index, variant, len)
+    }
+}
+
+/// Items of a compound value, each under a segment of its own.
+pub(super) struct Compound<'a> {
+    parent: FlatSerializer<'a>,
+    index: usize,
+    key: Option<String>,
+}
+
+impl<'a> Compound<'a> {
+    fn new(parent: FlatSerializer<'a>) -> Compound<'a> {
+        Compound {
+            parent,
+            index: 0,
+            key: None,
+        }
+    }
+
+    fn item<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TextError> {
+        let index = self.index;
+        self.index += 1;
+        value.serialize(self.parent.child(&index.to_string()))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TextError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TextError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TextError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TextError> {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), TextError> {
+        let key = key
+            .serialize(TextSerializer)
+            .ok()
+            .flatten()
+            .and_then(|key| String::from_utf8(key).ok())
+            .ok_or_else(|| TextError::Custom("flattened map keys must be scalars".into()))?;
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), TextError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value before serialize_key");
+        value.serialize(self.parent.child(&key))
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), TextError> {
+        value.serialize(self.parent.child(key))
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = TextError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), TextError> {
+        value.serialize(self.parent.child(key))
+    }
+
+    fn end(self) -> Result<(), TextError> {
+        Ok(())
+    }
+}
+
+/// Reads the value written by [`FlatSerializer`] under `prefix`.
+pub(super) struct FlatDeserializer<'de> {
+    pub(super) fields: &'de HashMap<Vec<u8>, Vec<u8>>,
+    pub(super) prefix: String,
+}
+
+impl<'de> FlatDeserializer<'de> {
+    /// The field named `prefix` itself.
+    fn leaf(&self) -> Option<&'de [u8]> {
+        self.fields.get(self.prefix.as_bytes()).map(Vec::as_slice)
+    }
+
+    /// The next segments of the fields under `prefix`, unescaped.
+    fn segments(&self) -> BTreeSet<Cow<'de, str>> {
+        self.fields
+            .keys()
+            .filter_map(|name| {
+                let rest = name
+                    .strip_prefix(self.prefix.as_bytes())?
+                    .strip_prefix(b".")?;
+                std::str::from_utf8(rest).ok().map(first_segment)
+            })
+            .collect()
+    }
+
+    fn child(&self, segment: &str) -> FlatDeserializer<'de> {
+        FlatDeserializer {
+            fields: self.fields,
+            prefix: format!("{}.{}", self.prefix, escape(segment)),
+        }
+    }
+
+    fn exists(&self) -> bool {
+        self.leaf().is_some() || !self.segments().is_empty()
+    }
+
+    fn text(&self) -> Result<TextDeserializer<'de>, TextError> {
+        self.leaf()
+            .map(TextDeserializer)
+            .ok_or_else(|| TextError::Custom(format!("missing field `{}`", self.prefix)))
+    }
+
+    fn items(&self, len: usize) -> Items<'de> {
+        Items {
+            parent: FlatDeserializer {
+                fields: self.fields,
+                prefix: self.prefix.clone(),
+            },
+            next: 0,
+            len,
+        }
+    }
+}
+
+macro_rules! deserialize_text {
+    ($($method:ident,)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+            self.text()?.$method(visitor)
+        })*
+    };
+}
+
+impl<'de> Deserializer<'de> for FlatDeserializer<'de> {
+    type Error = TextError;
+
+    deserialize_text! {
+        deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64,
+        deserialize_i128, deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64,
+        deserialize_u128, deserialize_f32, deserialize_f64, deserialize_char, deserialize_str,
+        deserialize_string, deserialize_bytes, deserialize_byte_buf, deserialize_identifier,
+    }
+
+    /// A scalar as text if there is a field named `prefix`, else a map of the fields under it.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        match self.leaf() {
+            Some(b) => TextDeserializer(b).deserialize_any(visitor),
+            None => self.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        if self.exists() {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// As many items as up to the last index there is a field for, at most [`MAX_LEN`].
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        let len = self
+            .segments()
+            .iter()
+            .filter_map(|segment| segment.parse::<usize>().ok())
+            .max()
+            .map_or(0, |last| last.saturating_add(1));
+        if len > MAX_LEN {
+            return Err(TextError::Custom(format!(
+                "`{}` has more than {} items",
+                self.prefix, MAX_LEN
+            )));
+        }
+        visitor.visit_seq(self.items(len))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        visitor.visit_seq(self.items(len))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        visitor.visit_seq(self.items(len))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        let entries = self.segments().into_iter().collect::<Vec<_>>().into_iter();
+        visitor.visit_map(Entries {
+            parent: self,
+            entries,
+            value: None,
+        })
+    }
+
+    /// All the fields, since empty sequences and maps are stored as no field at all.
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        let entries: Vec<Cow<'de, str>> = fields.iter().copied().map(Cow::Borrowed).collect();
+        visitor.visit_map(Entries {
+            parent: self,
+            entries: entries.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        if let Some(b) = self.leaf() {
+            return visitor.visit_enum(Variant {
+                name: Cow::Borrowed(TextDeserializer(b).text()?),
+                value: None,
+            });
+        }
+        let segments = self.segments();
+        let mut segments = segments.into_iter();
+        match (segments.next(), segments.next()) {
+            (Some(name), None) => visitor.visit_enum(Variant {
+                value: Some(self.child(&name)),
+                name,
+            }),
+            (None, _) => Err(TextError::Custom(format!(
+                "missing field `{}`",
+                self.prefix
+            ))),
+            (Some(_), Some(_)) => Err(TextError::Custom(format!(
+                "more than one variant under `{}`",
+                self.prefix
+            ))),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, TextError> {
+        visitor.visit_unit()
+    }
+}
+
+/// Items at the indexes `0..len`.
+struct Items<'de> {
+    parent: FlatDeserializer<'de>,
+    next: usize,
+    len: usize,
+}
+
+impl<'de> SeqAccess<'de> for Items<'de> {
+    type Error = TextError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, TextError> {
+        if self.next == self.len {
+            return Ok(None);
+        }
+        let item = self.parent.child(&self.next.to_string());
+        self.next += 1;
+        seed.deserialize(item).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.next)
+    }
+}
+
+/// Map entries or struct fields, keyed by the segments under the parent.
+struct Entries<'de, I> {
+    parent: FlatDeserializer<'de>,
+    entries: I,
+    value: Option<FlatDeserializer<'de>>,
+}
+
+impl<'de, I: Iterator<Item = Cow<'de, str>>> MapAccess<'de> for Entries<'de, I> {
+    type Error = TextError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, TextError> {
+        match self.entries.next() {
+            Some(key) => {
+                self.value = Some(self.parent.child(&key));
+                deserialize_segment(seed, key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, TextError> {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed before next_key_seed");
+        seed.deserialize(value)
+    }
+}
+
+/// A unit variant stored as its name, or another variant with the fields under its name.
+struct Variant<'de> {
+    name: Cow<'de, str>,
+    value: Option<FlatDeserializer<'de>>,
+}
+
+impl<'de> EnumAccess<'de> for Variant<'de> {
+    type Error = TextError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), TextError> {
+        let variant = deserialize_segment(seed, self.name.clone())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> Variant<'de> {
+    fn value(self) -> Result<FlatDeserializer<'de>, TextError> {
+        self.value.ok_or_else(|| {
+            TextError::Custom(format!(
+                "variant `{}` is stored as a unit variant",
+                self.name
+            ))
+        })
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'de> {
+    type Error = TextError;
+
+    fn unit_variant(self) -> Result<(), TextError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, TextError> {
+        seed.deserialize(self.value()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, TextError> {
+        self.value()?.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, TextError> {
+        self.value()?.deserialize_struct("", fields, visitor)
+    }
+}
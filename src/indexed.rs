@@ -0,0 +1,95 @@
+//! Reading slices of indexed sequences with `GETRANGE`. See [`ya_binary_format::indexed`].
+use std::ops::Range;
+
+use redis::{ConnectionLike, RedisResult, ToRedisArgs};
+use serde::de::DeserializeOwned;
+
+use crate::{codec::decode_error, Error, Options};
+
+pub use ya_binary_format::indexed::*;
+
+/// Number of items of the indexed sequence at `key`, or 0 if there is none.
+pub fn get_len<C, K>(con: &mut C, key: K) -> RedisResult<usize>
+where
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    let header = get_range_bytes(con, &key, 0..HEADER_SIZE)?;
+    if header.is_empty() {
+        return Ok(0);
+    }
+    count(&header).map_err(decode_error)
+}
+
+/// Items in `range` of the indexed sequence at `key`, each decoded with `options`.
+///
+/// The range is limited to the items there are, and a missing key has none.
+/// This reads the count, the index entries around the range and the items with three `GETRANGE`s.
+/// The key is `WATCH`ed and the last read is done in a `MULTI`, which is retried if the key was
+/// written in between, so the items are those of a single value. As `WATCH` applies to the
+/// connection, `con` must not be shared with other clients meanwhile.
+pub fn get_range<T, C, K>(
+    con: &mut C,
+    key: K,
+    options: &Options,
+    range: Range<usize>,
+) -> RedisResult<Vec<T>>
+where
+    T: DeserializeOwned,
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    let (index, items) = redis::transaction(con, &[&key], |con, pipe| {
+        // decoding errors are reported only once the key is known not to have changed
+        let index = get_index(con, &key, range.clone())?;
+        let bytes = index.as_ref().map_or(0..0, Index::bytes_range);
+        if bytes.is_empty() {
+            // an empty transaction would not be sent at all
+            pipe.cmd("EXISTS").arg(&key).ignore();
+        } else {
+            pipe.cmd("GETRANGE")
+                .arg(&key)
+                .arg(bytes.start)
+                .arg(bytes.end - 1);
+        }
+        let items: Option<Vec<Vec<u8>>> = pipe.query(con)?;
+        Ok(items.map(|items| (index, items.concat())))
+    })?;
+    index
+        .and_then(|index| index.decode(options, &items))
+        .map_err(decode_error)
+}
+
+/// The index entries locating `range` in the indexed sequence at `key`.
+fn get_index<C, K>(con: &mut C, key: &K, range: Range<usize>) -> RedisResult<Result<Index, Error>>
+where
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    let header = get_range_bytes(con, key, 0..HEADER_SIZE)?;
+    let count = match header.is_empty() {
+        true => 0,
+        false => match count(&header) {
+            Ok(count) => count,
+            Err(e) => return Ok(Err(e)),
+        },
+    };
+    let entries = get_range_bytes(con, key, Index::entries_range(count, range.clone()))?;
+    Ok(Index::parse(count, range, &entries))
+}
+
+/// `GETRANGE` of the bytes in `range`, which is empty for an empty range.
+fn get_range_bytes<C, K>(con: &mut C, key: &K, range: Range<usize>) -> RedisResult<Vec<u8>>
+where
+    C: ConnectionLike,
+    K: ToRedisArgs,
+{
+    if range.is_empty() {
+        return Ok(Vec::new());
+    }
+    redis::cmd("GETRANGE")
+        .arg(key)
+        .arg(range.start)
+        .arg(range.end - 1)
+        .query(con)
+}
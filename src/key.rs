@@ -0,0 +1,93 @@
+//! Composite keys formatted from and parsed back into their fields. See [`RedisKey`](crate::RedisKey).
+//!
+//! Fields are separated by `:`, and `:` and `\` inside them are escaped with `\`,
+//! so that any value of a field parses back to itself.
+use std::{borrow::Cow, error, fmt};
+
+/// Escape `:` and `\` in a field.
+pub fn escape(field: &str) -> Cow<'_, str> {
+    if !field.contains([':', '\\']) {
+        return Cow::Borrowed(field);
+    }
+    let mut escaped = String::with_capacity(field.len() + 2);
+    for c in field.chars() {
+        if c == ':' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Split the fields of `key` at unescaped `:`s, and unescape them.
+pub fn split(key: &str) -> Result<Vec<String>, ParseKeyError> {
+    let mut fields = vec![String::new()];
+    let mut chars = key.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' => fields.push(String::new()),
+            '\\' => match chars.next() {
+                Some(c @ (':' | '\\')) => fields.last_mut().unwrap().push(c),
+                _ => return Err(ParseKeyError::new(key, "invalid escape")),
+            },
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    Ok(fields)
+}
+
+/// The `n` unescaped fields of `key` after `prefix`.
+pub fn parse(key: &str, prefix: &str, n: usize) -> Result<Vec<String>, ParseKeyError> {
+    let rest = if prefix.is_empty() {
+        Some(key)
+    } else if n == 0 {
+        (key == prefix).then_some("")
+    } else {
+        key.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix(':'))
+    };
+    let rest =
+        rest.ok_or_else(|| ParseKeyError::new(key, format!("expected the prefix {:?}", prefix)))?;
+    if n == 0 {
+        return match rest {
+            "" => Ok(Vec::new()),
+            _ => Err(ParseKeyError::new(key, "expected no fields")),
+        };
+    }
+    let fields = split(rest).map_err(|e| ParseKeyError::new(key, e.reason))?;
+    if fields.len() != n {
+        return Err(ParseKeyError::new(
+            key,
+            format!("expected {} fields, found {}", n, fields.len()),
+        ));
+    }
+    Ok(fields)
+}
+
+/// A key which does not match the format of the key type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError {
+    key: String,
+    reason: String,
+}
+
+impl ParseKeyError {
+    pub fn new(key: &str, reason: impl Into<String>) -> ParseKeyError {
+        ParseKeyError {
+            key: key.to_owned(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key {:?}: {}", self.key, self.reason)
+    }
+}
+
+impl error::Error for ParseKeyError {}
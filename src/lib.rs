@@ -46,7 +46,162 @@ let a2: Option<MyStruct> = redis_con.get("key-a").expect("Fail to get");
 assert!(a2.is_some());
 assert_eq!(a, a2.unwrap());
 # }
+```
+
+## Container attributes
+
+- `#[redis(dictionary)]`: write each distinct string once per value and refer to it by index afterward.
+- `#[redis(packed_flags)]`: collect the `bool`s and `Option` tags of each struct into a bitmap.
+- `#[redis(checksum)]`: append a CRC32C trailer which is verified before decoding.
+- `#[redis(compress)]`, `#[redis(compress, compress_above = 4096)]`: compress values larger than
+  the threshold (1024 bytes by default) with LZ4. Requires the `compress` feature (on by default).
+- `#[redis(encrypt, key_provider = path::to::KEYS)]`: encrypt the whole value with ChaCha20-Poly1305
+  using keys from a static `KeyProvider`. Requires the `encrypt` feature.
+- `#[redis(view)]`: also generate `MyStructView<'a>`, which keeps the encoded bytes and decodes
+  a field only when its accessor is called, skipping the fields before it. Use it to read a few
+  fields of large values. Requires `#[derive(RedisSchema)]` and a non-generic struct with named fields,
+  none of them named `new`, which constructs the view. Only the fields of the struct itself are
+  viewed: sequences of structs, like `V(Vec<A>)`, have no view, and their items are decoded whole.
+- `#[redis(fixed_layout)]`: for structs of fixed size fields (numbers, `bool`s, arrays, tuples and
+  other `fixed_layout` structs), implement [`FixedSize`] and generate a `FIELD_OFFSET` constant and
+  `get_field(con, key)`/`set_field(con, key, &value)` per field, which read or overwrite only that
+  field with `GETRANGE`/`SETRANGE`. Cannot be combined with options that change the bytes as a whole
+  or with `packed_flags`, and its fields take no serde attributes other than `#[serde(skip)]`.
+- `#[redis(indexed)]`: for a newtype over a `Vec`, store an index of item offsets before the items,
+  each encoded on its own with the other options, and generate `get_range(con, key, range)` and
+  `get_len(con, key)`, which read only the index and the requested items with `GETRANGE`.
+  `get_range` `WATCH`es the key to read them from a single value, so it needs a connection of its own.
+- `#[redis(codec = path::to::CODEC)]`: store values with another [`Codec`] instead of the ya binary
+  format, like [`codec::Json`] (with the `json` feature, on by default) or one of your own.
+  Encoding errors panic, since `ToRedisArgs` cannot fail. The options above, which belong to the
+  ya binary format, cannot be combined with it.
+- `#[redis(fallback = path::to::CODEC)]`: also read values stored with another [`Codec`], to switch
+  formats without flushing existing keys, like from `serde_json` with `fallback = Json`.
+  It is tried after the ya binary format, so it requires `checksum`, which keeps legacy values from
+  decoding as binary (a `u64` stored by `serde_json` as `10000000` is 8 bytes long), and cannot be
+  combined with `codec`.
+  Also generates `get_migrating(con, key)`, which rewrites such values in the current format,
+  unless they changed meanwhile, keeping their TTL (Redis 6.0 or later).
+- `#[redis(hash)]`: for structs with named fields, store a Redis hash with a field per struct field
+  instead of a single value: `ToRedisArgs` writes the field names and values for `HSET`, and
+  `FromRedisValue` reads the reply of `HGETALL`. Numbers, `bool`s, `char`s and strings are stored
+  as text, and other values encoded with the other options. `None` fields are not stored, so
+  `HSET` leaves those of a previous value: use it only on a fresh key, and the generated
+  `save(&self, con, key)`, which runs `DEL` and `HSET` in a `MULTI`, to replace a hash.
+  Also generates `get_field(con, key)` and `set_field(con, key, &value)` per field, which read or
+  write only that field with `HGET`/`HSET`, or `HDEL` for `None`.
+- `#[redis(as_str)]`: for enums with only unit variants, store the name of the variant as a plain
+  string, readable in `redis-cli` and usable in sets or by other services, and generate
+  `as_str(&self)`. Add `rename_all = "snake_case"` or another case rule of serde to rename
+  the variants. Reading an unknown name fails with an error listing the expected ones.
+- `#[redis(transparent)]`: for structs with a single field, like `struct Counter(i64)`, store
+  the field as redis itself does, so integers, floats, strings and `bool`s are stored as text which
+  `INCR`, `INCRBYFLOAT` or Lua's `tonumber` work on, and read back from integer, bulk or
+  status replies.
+- `#[redis(reply = "array")]`: only implement `FromRedisValue`, reading the fields from the items of
+  an array reply by position, like that of `HMGET` or of a Lua script. With `reply = "map"`, read
+  them from alternating names and values instead, like the replies of `XINFO` or `MEMORY STATS`,
+  renamed with `rename_all` or `#[redis(rename = "...")]`. Each field is read with its own
+  `FromRedisValue`, from `Nil` if its item is missing, and extra items are ignored.
+- `#[redis(args)]`: only implement `ToRedisArgs`, writing the fields as command arguments in their
+  order, each with its own `ToRedisArgs`, so `None` fields write nothing. Use it to type the options
+  of a command, like `SET key value EX 10 NX`, or the `ARGV` of a script.
+- `#[redis(key = "user:{id}")]`: for structs with named fields, also generate `redis_key(&self)`
+  and `key_for(&id)`, which format the key from the fields in braces, with `{{` and `}}` for
+  literal braces. The values are formatted as is, like in a hand-written `format!`, so a `:` in one
+  can make it the key of other values: add `key_escape` to escape `:` and `\` with `\`, like
+  [`key::escape`] does, which changes the keys of such values.
+  Unknown fields and fields which do not implement `Display` fail to compile.
+  It can be combined with any of the attributes above.
+
+## Field attributes
+
+- `#[redis(encrypt)]`: encrypt the field with the container's `key_provider`.
+  Fields are matched by their serde names, so `#[serde(rename)]` and `rename_all` are taken into
+  account. Only the options of the stored type apply: the encrypted fields of a type nested inside
+  another value are written in plaintext, so mark the field holding it `#[redis(encrypt)]` instead.
+  Encrypted fields are encoded on their own, so they share no dictionary entries or flags with the rest.
+- `#[redis(flatten)]`: with `#[redis(hash)]`, store every scalar inside the field in a hash field of
+  its own, named by its path like `address.city` or `scores.0`, so that each can be read and
+  written with `HGET`, `HSET` or `HINCRBY`. See [`hash`] for the names of items and variants.
+- `#[redis(counter)]`: with `#[redis(hash)]`, on a float field or an integer one which fits in the
+  `i64` of `HINCRBY` (not `u64` or `usize`), also generate `incr_field(con, key, delta)`, which adds
+  to the field with `HINCRBY` or `HINCRBYFLOAT` and returns the new value, so that concurrent
+  updates need no read-modify-write of the whole struct.
+- `#[redis(rename = "name")]`: with `#[redis(reply = "map")]`, the name of the field in the reply.
+- `#[redis(flag = "NX")]`: with `#[redis(args)]`, on a `bool` field, write `NX` if it is `true`
+  and nothing otherwise.
+- `#[redis(token = "EX")]`: with `#[redis(args)]`, write `EX` before the arguments of the field,
+  unless it writes none, like `None`.
+
+## Keys
+
+`#[derive(RedisKey)]` on a small struct or tuple struct implements `Display`, `FromStr`,
+`ToRedisArgs` and `FromRedisValue` for it as a key like `user:{tenant}:{id}`, so that the keys
+returned by `SCAN` or `KEYS` parse back into typed keys. Fields are written with `Display`, read
+with `FromStr`, and separated by `:`, and `:` and `\` inside them are escaped with `\`. The prefix
+is the snake case name of the type without a `Key` suffix, or `#[redis(prefix = "...")]`.
+See [`key`].
+
+```rust
+use ya_redis_derive::RedisKey;
+
+#[derive(Debug, PartialEq, RedisKey)]
+struct UserKey {
+    tenant: u32,
+    id: i64,
+}
+
+let key = UserKey { tenant: 1, id: 42 };
+assert_eq!(key.to_string(), "user:1:42");
+assert_eq!("user:1:42".parse::<UserKey>().unwrap(), key);
+```
+
+## Schema
+
+`#[derive(RedisSchema)]` implements [`RedisSchema`], which describes the fields, types, variants
+and encoding options of a stored value at runtime. It reads the same `#[redis(...)]` attributes
+and knows the `#[serde(with = "...")]` modules of [`encoding`], `#[serde(skip)]` and the names
+given by `rename`, `rename_all` and `rename_all_fields`. Serde attributes which change the encoded
+value in other ways, like `skip_serializing_if`, `flatten` or other `with` modules, fail to compile.
+Every field type must implement `RedisSchema` too, so derive it on nested types as well.
+
+```rust
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::{schema::Schema, Redis, RedisSchema};
+
+#[derive(Redis, RedisSchema, Deserialize, Serialize)]
+#[redis(dictionary)]
+struct MyStruct {
+    id: i64,
+    tags: Vec<String>,
+}
+
+let schema = MyStruct::value_schema();
+assert!(schema.options.dictionary);
+assert!(matches!(schema.schema, Schema::Struct { .. }));
+```
+
+Saved as JSON, the value schema lets the `ya-inspect` command line tool in this workspace
+decode stored values to JSON, encode JSON back, and explain which bytes hold what:
+
+```text
+redis-cli GET my-key | ya-inspect explain --schema my-struct.json --format redis-cli
 ```
  */
-pub use ya_binary_format::{from_bytes, to_bytes};
-pub use ya_redis_proc_macro::Redis;
+pub mod codec;
+pub mod fixed;
+pub mod hash;
+pub mod indexed;
+pub mod key;
+pub mod reply;
+pub mod schema;
+
+pub use codec::Codec;
+pub use fixed::FixedSize;
+pub use schema::RedisSchema;
+pub use ya_binary_format::{encoding, from_bytes, to_bytes, view, Error, Options};
+pub use ya_redis_proc_macro::{Redis, RedisKey, RedisSchema};
+
+#[cfg(feature = "encrypt")]
+pub use ya_binary_format::KeyProvider;
@@ -0,0 +1,50 @@
+//! Reading structs out of array replies. See `#[redis(reply = "...")]`.
+use std::collections::HashMap;
+
+use redis::{ErrorKind, FromRedisValue, RedisError, RedisResult, Value};
+
+/// Items of an array reply.
+pub fn items(v: &Value) -> RedisResult<&[Value]> {
+    match v {
+        Value::Bulk(items) => Ok(items),
+        _ => Err(RedisError::from((
+            ErrorKind::TypeError,
+            "the reply got from redis was not an array",
+        ))),
+    }
+}
+
+/// Values by name of an array reply of alternating names and values, like that of `HGETALL`.
+///
+/// A name given twice has the last value.
+pub fn entries(v: &Value) -> RedisResult<HashMap<&[u8], &Value>> {
+    let items = items(v)?;
+    if items.len() % 2 != 0 {
+        return Err(RedisError::from((
+            ErrorKind::TypeError,
+            "the reply got from redis was not pairs of names and values",
+        )));
+    }
+    items
+        .chunks(2)
+        .map(|pair| match &pair[0] {
+            Value::Data(name) => Ok((&name[..], &pair[1])),
+            Value::Status(name) => Ok((name.as_bytes(), &pair[1])),
+            _ => Err(RedisError::from((
+                ErrorKind::TypeError,
+                "the reply got from redis had a name which was not a string",
+            ))),
+        })
+        .collect()
+}
+
+/// Read the field `name` from its item, or from `Nil` if there is none.
+pub fn field<T: FromRedisValue>(item: Option<&Value>, name: &str) -> RedisResult<T> {
+    T::from_redis_value(item.unwrap_or(&Value::Nil)).map_err(|e| {
+        RedisError::from((
+            ErrorKind::TypeError,
+            "failed to read a field of the reply got from redis",
+            format!("{}: {}", name, e),
+        ))
+    })
+}
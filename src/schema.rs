@@ -0,0 +1,195 @@
+//! Runtime description of stored values. See [`RedisSchema`].
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, LinkedList, VecDeque},
+    rc::Rc,
+    sync::Arc,
+};
+
+pub use ya_binary_format::schema::*;
+
+/// Runtime description of how a type is encoded.
+///
+/// `#[derive(RedisSchema)]` implements it for structs and enums;
+/// every field type must implement it as well.
+pub trait RedisSchema {
+    /// Layout of the type.
+    fn schema() -> Schema;
+
+    /// Layout of a value stored with the type and the options it is encoded with.
+    fn value_schema() -> ValueSchema {
+        ValueSchema {
+            options: Default::default(),
+            schema: Self::schema(),
+        }
+    }
+}
+
+thread_local! {
+    static IN_PROGRESS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Build the schema of a named type, returning [`Schema::Ref`] when it is reached again while building.
+#[doc(hidden)]
+pub fn named<T: ?Sized>(name: &str, f: impl FnOnce() -> Schema) -> Schema {
+    let id = std::any::type_name::<T>();
+    if IN_PROGRESS.with(|p| p.borrow().contains(&id)) {
+        return Schema::Ref { name: name.into() };
+    }
+    IN_PROGRESS.with(|p| p.borrow_mut().push(id));
+    let schema = f();
+    IN_PROGRESS.with(|p| p.borrow_mut().pop());
+    schema
+}
+
+macro_rules! impl_primitive {
+    ($($t:ty => $s:ident),* $(,)?) => {
+        $(
+            impl RedisSchema for $t {
+                fn schema() -> Schema {
+                    Schema::$s
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(
+    bool => Bool,
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    isize => I64,
+    i128 => I128,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    usize => U64,
+    u128 => U128,
+    f32 => F32,
+    f64 => F64,
+    char => Char,
+    str => String,
+    String => String,
+    () => Unit,
+);
+
+macro_rules! impl_wrapper {
+    ($($t:ident),*) => {
+        $(
+            impl<T: ?Sized + RedisSchema> RedisSchema for $t<T> {
+                fn schema() -> Schema {
+                    T::schema()
+                }
+            }
+        )*
+    };
+}
+
+impl_wrapper!(Box, Rc, Arc);
+
+impl<T: ?Sized + RedisSchema> RedisSchema for &T {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: ?Sized + ToOwned + RedisSchema> RedisSchema for Cow<'_, T> {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: RedisSchema> RedisSchema for Option<T> {
+    fn schema() -> Schema {
+        Schema::Option {
+            item: Box::new(T::schema()),
+        }
+    }
+}
+
+macro_rules! impl_seq {
+    ($($t:ident),*) => {
+        $(
+            impl<T: RedisSchema> RedisSchema for $t<T> {
+                fn schema() -> Schema {
+                    Schema::Seq {
+                        item: Box::new(T::schema()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_seq!(Vec, VecDeque, LinkedList, BTreeSet);
+
+impl<T: RedisSchema, S> RedisSchema for HashSet<T, S> {
+    fn schema() -> Schema {
+        Schema::Seq {
+            item: Box::new(T::schema()),
+        }
+    }
+}
+
+impl<T: RedisSchema> RedisSchema for [T] {
+    fn schema() -> Schema {
+        Schema::Seq {
+            item: Box::new(T::schema()),
+        }
+    }
+}
+
+impl<T: RedisSchema, const N: usize> RedisSchema for [T; N] {
+    fn schema() -> Schema {
+        Schema::Tuple {
+            items: (0..N).map(|_| T::schema()).collect(),
+        }
+    }
+}
+
+impl<K: RedisSchema, V: RedisSchema> RedisSchema for BTreeMap<K, V> {
+    fn schema() -> Schema {
+        Schema::Map {
+            key: Box::new(K::schema()),
+            value: Box::new(V::schema()),
+        }
+    }
+}
+
+impl<K: RedisSchema, V: RedisSchema, S> RedisSchema for HashMap<K, V, S> {
+    fn schema() -> Schema {
+        Schema::Map {
+            key: Box::new(K::schema()),
+            value: Box::new(V::schema()),
+        }
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($t:ident)+) => {
+        impl<$($t: RedisSchema),+> RedisSchema for ($($t,)+) {
+            fn schema() -> Schema {
+                Schema::Tuple {
+                    items: vec![$($t::schema()),+],
+                }
+            }
+        }
+    };
+}
+
+impl_tuple!(T0);
+impl_tuple!(T0 T1);
+impl_tuple!(T0 T1 T2);
+impl_tuple!(T0 T1 T2 T3);
+impl_tuple!(T0 T1 T2 T3 T4);
+impl_tuple!(T0 T1 T2 T3 T4 T5);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10);
+impl_tuple!(T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11);
@@ -0,0 +1,64 @@
+use redis::ToRedisArgs;
+use ya_redis_derive::Redis;
+
+#[derive(Debug, Default, Redis)]
+#[redis(args)]
+struct SetOptions {
+    #[redis(token = "EX")]
+    ex: Option<u64>,
+    #[redis(token = "PX")]
+    px: Option<u64>,
+    #[redis(flag = "NX")]
+    nx: bool,
+    #[redis(flag = "XX")]
+    xx: bool,
+    #[redis(flag = "KEEPTTL")]
+    keep_ttl: bool,
+}
+
+#[derive(Debug, Redis)]
+#[redis(args)]
+struct Argv(
+    String,
+    Option<i64>,
+    Vec<String>,
+    #[redis(token = "LIMIT")] (u32, u32),
+);
+
+fn args(v: &impl ToRedisArgs) -> Vec<String> {
+    v.to_redis_args()
+        .into_iter()
+        .map(|arg| String::from_utf8(arg).unwrap())
+        .collect()
+}
+
+#[test]
+fn options() {
+    let options = SetOptions {
+        ex: Some(10),
+        nx: true,
+        ..SetOptions::default()
+    };
+    assert_eq!(args(&options), ["EX", "10", "NX"]);
+    assert!(!options.is_single_arg());
+    assert!(args(&SetOptions::default()).is_empty());
+    let options = SetOptions {
+        px: Some(5),
+        xx: true,
+        keep_ttl: true,
+        ..SetOptions::default()
+    };
+    assert_eq!(args(&options), ["PX", "5", "XX", "KEEPTTL"]);
+
+    let cmd = redis::cmd("SET").arg("k").arg("v").arg(&options).clone();
+    let packed = String::from_utf8(cmd.get_packed_command()).unwrap();
+    assert!(packed.starts_with("*7\r\n"), "{:?}", packed);
+}
+
+#[test]
+fn positional() {
+    let argv = Argv("a".into(), None, vec!["b".into(), "c".into()], (0, 10));
+    assert_eq!(args(&argv), ["a", "b", "c", "LIMIT", "0", "10"]);
+    let argv = Argv("a".into(), Some(-1), vec![], (1, 2));
+    assert_eq!(args(&argv), ["a", "-1", "LIMIT", "1", "2"]);
+}
@@ -0,0 +1,51 @@
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use ya_redis_derive::Redis;
+
+#[derive(Debug, Clone, Copy, PartialEq, Redis)]
+#[redis(as_str)]
+enum Color {
+    Red,
+    DarkBlue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Redis)]
+#[redis(as_str, rename_all = "snake_case")]
+enum Status {
+    Active,
+    OnHold,
+    Closed,
+}
+
+#[test]
+fn names() {
+    assert_eq!(Color::DarkBlue.to_redis_args(), [b"DarkBlue".to_vec()]);
+    assert_eq!(Status::OnHold.to_redis_args(), [b"on_hold".to_vec()]);
+    assert_eq!(Status::Closed.as_str(), "closed");
+    assert_eq!(Status::Active.as_str(), "active");
+
+    assert_eq!(
+        Color::from_redis_value(&Value::Data(b"Red".to_vec())).unwrap(),
+        Color::Red
+    );
+    assert_eq!(
+        Status::from_redis_value(&Value::Status("on_hold".into())).unwrap(),
+        Status::OnHold
+    );
+    let v: Vec<Status> = FromRedisValue::from_redis_value(&Value::Bulk(vec![
+        Value::Data(b"closed".to_vec()),
+        Value::Data(b"active".to_vec()),
+    ]))
+    .unwrap();
+    assert_eq!(v, [Status::Closed, Status::Active]);
+}
+
+#[test]
+fn unknown() {
+    let e = Status::from_redis_value(&Value::Data(b"OnHold".to_vec())).unwrap_err();
+    assert_eq!(e.kind(), redis::ErrorKind::TypeError);
+    assert_eq!(
+        e.detail(),
+        Some("\"OnHold\" is not a variant of Status, expected one of active, on_hold, closed")
+    );
+    assert!(Color::from_redis_value(&Value::Int(0)).is_err());
+}
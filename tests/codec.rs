@@ -0,0 +1,98 @@
+mod common;
+
+use common::MockRedis;
+use redis::{Commands, FromRedisValue, ToRedisArgs, Value};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use ya_redis_derive::{
+    codec::{Codec, Json},
+    Options, Redis,
+};
+
+#[derive(Debug, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(codec = Json)]
+struct Session {
+    user_id: u64,
+    scopes: Vec<String>,
+}
+
+#[test]
+fn json() {
+    let mut con = MockRedis::new();
+    let session = Session {
+        user_id: 7,
+        scopes: vec!["read".into()],
+    };
+    let _: () = con.set("session", &session).unwrap();
+    assert_eq!(
+        con.strings[&b"session"[..]],
+        br#"{"user_id":7,"scopes":["read"]}"#
+    );
+    let got: Session = con.get("session").unwrap();
+    assert_eq!(got, session);
+
+    let e = Session::from_redis_value(&Value::Data(b"{".to_vec())).unwrap_err();
+    assert_eq!(e.kind(), redis::ErrorKind::TypeError);
+}
+
+/// The ya binary format behind a version byte.
+struct Versioned;
+
+static VERSIONED: Versioned = Versioned;
+
+impl Codec for Versioned {
+    type Error = String;
+
+    fn encode<T: ?Sized + Serialize>(&self, value: &T) -> Result<Vec<u8>, String> {
+        let mut b = vec![1];
+        b.extend(Options::new().checksum(true).to_bytes(value));
+        Ok(b)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, b: &[u8]) -> Result<T, String> {
+        match b.split_first() {
+            Some((1, b)) => Options::new()
+                .checksum(true)
+                .from_bytes(b)
+                .map_err(|e| e.to_string()),
+            _ => Err("unknown version".into()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(codec = VERSIONED)]
+struct Custom(i32, String);
+
+#[test]
+fn custom() {
+    let v = Custom(-1, "a".into());
+    let args = v.to_redis_args();
+    assert_eq!(args[0][0], 1);
+    assert_eq!(
+        args[0][1..],
+        Options::new().checksum(true).to_bytes(&(-1i32, "a"))
+    );
+    assert_eq!(
+        Custom::from_redis_value(&Value::Data(args[0].clone())).unwrap(),
+        v
+    );
+    let e = Custom::from_redis_value(&Value::Data(vec![2])).unwrap_err();
+    assert_eq!(e.detail(), Some("unknown version"));
+}
+
+#[derive(Debug, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(checksum)]
+struct Plain {
+    id: u8,
+}
+
+#[test]
+fn options_are_a_codec() {
+    let options = Options::new().checksum(true);
+    let b = options.encode(&Plain { id: 3 }).unwrap();
+    assert_eq!(Plain { id: 3 }.to_redis_args()[0], b);
+    assert_eq!(
+        Codec::decode::<Plain>(&options, &b).unwrap(),
+        Plain { id: 3 }
+    );
+}
@@ -0,0 +1,268 @@
+//! In-memory stand-in for a Redis server, for the commands the generated helpers send.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use redis::{ConnectionLike, ErrorKind, RedisError, RedisResult, Value};
+
+#[derive(Default)]
+pub struct MockRedis {
+    pub strings: HashMap<Vec<u8>, Vec<u8>>,
+    pub hashes: HashMap<Vec<u8>, HashMap<Vec<u8>, Vec<u8>>>,
+    /// Commands received, as their arguments.
+    pub log: Vec<Vec<Vec<u8>>>,
+    /// Commands of another client, run just before the next `EXEC`.
+    pub before_exec: Vec<Vec<Vec<u8>>>,
+    /// Values of the `WATCH`ed keys when they were watched.
+    watched: Vec<(Vec<u8>, Snapshot)>,
+    /// Commands queued since `MULTI`.
+    queued: Option<Vec<Vec<Vec<u8>>>>,
+}
+
+type Snapshot = (Option<Vec<u8>>, Option<HashMap<Vec<u8>, Vec<u8>>>);
+
+impl MockRedis {
+    pub fn new() -> MockRedis {
+        MockRedis::default()
+    }
+
+    fn snapshot(&self, key: &[u8]) -> Snapshot {
+        (
+            self.strings.get(key).cloned(),
+            self.hashes.get(key).cloned(),
+        )
+    }
+
+    fn run(&mut self, args: Vec<Vec<u8>>) -> RedisResult<Value> {
+        self.log.push(args.clone());
+        self.execute(args)
+    }
+
+    /// Run a command without logging it, like those queued since `MULTI` at `EXEC`.
+    fn execute(&mut self, args: Vec<Vec<u8>>) -> RedisResult<Value> {
+        let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+        if name != "EXEC" {
+            if let Some(queued) = &mut self.queued {
+                queued.push(args);
+                return Ok(Value::Status(String::from("QUEUED")));
+            }
+        }
+        let args = &args[1..];
+        match (name.as_str(), args) {
+            ("WATCH", keys) => {
+                for key in keys {
+                    self.watched.push((key.clone(), self.snapshot(key)));
+                }
+                Ok(Value::Okay)
+            }
+            ("UNWATCH", []) => {
+                self.watched.clear();
+                Ok(Value::Okay)
+            }
+            ("MULTI", []) => {
+                self.queued = Some(Vec::new());
+                Ok(Value::Okay)
+            }
+            ("EXEC", []) => {
+                let queued = self.queued.take().ok_or_else(|| {
+                    RedisError::from((ErrorKind::ResponseError, "EXEC without MULTI"))
+                })?;
+                for args in std::mem::take(&mut self.before_exec) {
+                    self.run(args)?;
+                }
+                // a changed value stands for a write, which is enough here
+                let watched = std::mem::take(&mut self.watched);
+                if watched
+                    .iter()
+                    .any(|(key, snapshot)| self.snapshot(key) != *snapshot)
+                {
+                    return Ok(Value::Nil);
+                }
+                Ok(Value::Bulk(
+                    queued
+                        .into_iter()
+                        .map(|args| self.execute(args))
+                        .collect::<RedisResult<_>>()?,
+                ))
+            }
+            ("EXISTS", keys) => Ok(Value::Int(
+                keys.iter()
+                    .filter(|k| self.strings.contains_key(*k) || self.hashes.contains_key(*k))
+                    .count() as i64,
+            )),
+            ("GET", [key]) => Ok(self
+                .strings
+                .get(key)
+                .map_or(Value::Nil, |v| Value::Data(v.clone()))),
+            ("SET", [key, value]) => {
+                self.strings.insert(key.clone(), value.clone());
+                Ok(Value::Okay)
+            }
+            ("DEL", keys) => Ok(Value::Int(
+                keys.iter()
+                    .filter(|k| {
+                        self.strings.remove(*k).is_some() | self.hashes.remove(*k).is_some()
+                    })
+                    .count() as i64,
+            )),
+            ("INCRBY", [key, delta]) => {
+                let v = self.strings.get(key).map_or(Ok(0), |v| int(v))? + int(delta)?;
+                self.strings.insert(key.clone(), v.to_string().into_bytes());
+                Ok(Value::Int(v))
+            }
+            ("GETRANGE", [key, start, end]) => {
+                let v = self.strings.get(key).cloned().unwrap_or_default();
+                let index = |i: i64| {
+                    if i < 0 {
+                        (v.len() as i64 + i).max(0) as usize
+                    } else {
+                        i as usize
+                    }
+                };
+                let start = index(int(start)?);
+                let end = index(int(end)?).min(v.len().saturating_sub(1));
+                Ok(Value::Data(if v.is_empty() || start > end {
+                    vec![]
+                } else {
+                    v[start..=end].to_vec()
+                }))
+            }
+            ("SETRANGE", [key, offset, value]) => {
+                let offset = int(offset)? as usize;
+                let v = self.strings.entry(key.clone()).or_default();
+                if v.len() < offset + value.len() {
+                    v.resize(offset + value.len(), 0);
+                }
+                v[offset..offset + value.len()].copy_from_slice(value);
+                Ok(Value::Int(v.len() as i64))
+            }
+            ("HSET", [key, pairs @ ..]) if !pairs.is_empty() && pairs.len() % 2 == 0 => {
+                let hash = self.hashes.entry(key.clone()).or_default();
+                let added = pairs
+                    .chunks(2)
+                    .filter(|pair| hash.insert(pair[0].clone(), pair[1].clone()).is_none())
+                    .count();
+                Ok(Value::Int(added as i64))
+            }
+            ("HGET", [key, field]) => Ok(self
+                .hashes
+                .get(key)
+                .and_then(|hash| hash.get(field))
+                .map_or(Value::Nil, |v| Value::Data(v.clone()))),
+            ("HDEL", [key, fields @ ..]) => {
+                let hash = self.hashes.entry(key.clone()).or_default();
+                Ok(Value::Int(
+                    fields.iter().filter(|f| hash.remove(*f).is_some()).count() as i64,
+                ))
+            }
+            ("HINCRBY", [key, field, delta]) => {
+                let hash = self.hashes.entry(key.clone()).or_default();
+                let v = hash.get(field).map_or(Ok(0), |v| int(v))? + int(delta)?;
+                hash.insert(field.clone(), v.to_string().into_bytes());
+                Ok(Value::Int(v))
+            }
+            ("HINCRBYFLOAT", [key, field, delta]) => {
+                let hash = self.hashes.entry(key.clone()).or_default();
+                let v = hash.get(field).map_or(Ok(0.0), |v| float(v))? + float(delta)?;
+                hash.insert(field.clone(), v.to_string().into_bytes());
+                Ok(Value::Data(v.to_string().into_bytes()))
+            }
+            ("HGETALL", [key]) => Ok(Value::Bulk(
+                self.hashes
+                    .get(key)
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|(field, v)| [Value::Data(field.clone()), Value::Data(v.clone())])
+                    .collect(),
+            )),
+            // only the compare-and-set script of `codec::get_migrating`
+            ("EVAL", [_script, n, key, old, new]) if int(n)? == 1 => {
+                if self.strings.get(key) == Some(old) {
+                    self.strings.insert(key.clone(), new.clone());
+                    Ok(Value::Okay)
+                } else {
+                    Ok(Value::Nil)
+                }
+            }
+            _ => Err(RedisError::from((
+                ErrorKind::ResponseError,
+                "unknown command",
+                name,
+            ))),
+        }
+    }
+}
+
+fn int(arg: &[u8]) -> RedisResult<i64> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| RedisError::from((ErrorKind::ResponseError, "not an integer")))
+}
+
+fn float(arg: &[u8]) -> RedisResult<f64> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| RedisError::from((ErrorKind::ResponseError, "not a float")))
+}
+
+/// Commands in RESP, as `redis::Cmd` packs them.
+fn parse(mut b: &[u8]) -> Vec<Vec<Vec<u8>>> {
+    fn line<'a>(b: &mut &'a [u8]) -> &'a [u8] {
+        let n = b.windows(2).position(|w| w == b"\r\n").unwrap();
+        let line = &b[..n];
+        *b = &b[n + 2..];
+        line
+    }
+    fn number(line: &[u8]) -> usize {
+        std::str::from_utf8(&line[1..]).unwrap().parse().unwrap()
+    }
+    let mut commands = Vec::new();
+    while !b.is_empty() {
+        let n = number(line(&mut b));
+        let args = (0..n)
+            .map(|_| {
+                let len = number(line(&mut b));
+                let arg = b[..len].to_vec();
+                b = &b[len + 2..];
+                arg
+            })
+            .collect();
+        commands.push(args);
+    }
+    commands
+}
+
+impl ConnectionLike for MockRedis {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> RedisResult<Value> {
+        let mut commands = parse(cmd);
+        assert_eq!(commands.len(), 1);
+        self.run(commands.pop().unwrap())
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        let replies = parse(cmd)
+            .into_iter()
+            .map(|args| self.run(args))
+            .collect::<RedisResult<Vec<_>>>()?;
+        Ok(replies.into_iter().skip(offset).take(count).collect())
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+
+    fn check_connection(&mut self) -> bool {
+        true
+    }
+
+    fn is_open(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,94 @@
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::Redis;
+
+#[derive(Debug, Eq, PartialEq, Redis, Deserialize, Serialize)]
+struct Encoded {
+    #[serde(with = "ya_redis_derive::encoding::delta")]
+    ids: Vec<i64>,
+    #[serde(with = "ya_redis_derive::encoding::delta_of_delta")]
+    timestamps: Vec<u64>,
+    #[serde(with = "ya_redis_derive::encoding::rle")]
+    levels: Vec<i8>,
+}
+
+#[test]
+fn integer_sequences() {
+    let v = Encoded {
+        ids: (0..1000).map(|i| 1_000_000_000 + i * 3).collect(),
+        timestamps: (0..1000).map(|i| 1_660_000_000_000 + i * 60_000).collect(),
+        levels: [vec![0; 500], vec![-3; 499], vec![i8::MIN]].concat(),
+    };
+    let mut args = v.to_redis_args();
+    assert_eq!(args.len(), 1);
+    assert!(args[0].len() < 2100);
+    let v2 = Encoded::from_redis_value(&Value::Data(args.pop().unwrap())).unwrap();
+    assert_eq!(v, v2);
+}
+
+#[test]
+fn integer_sequences_extreme() {
+    let v = Encoded {
+        ids: vec![i64::MAX, i64::MIN, 0, -1, i64::MAX],
+        timestamps: vec![u64::MAX, 0, u64::MAX, 1],
+        levels: vec![],
+    };
+    let mut args = v.to_redis_args();
+    let v2 = Encoded::from_redis_value(&Value::Data(args.pop().unwrap())).unwrap();
+    assert_eq!(v, v2);
+}
+
+#[derive(Debug, Eq, PartialEq, Redis, Deserialize, Serialize)]
+struct Narrow {
+    #[serde(with = "ya_redis_derive::encoding::delta")]
+    ids: Vec<i16>,
+    #[serde(with = "ya_redis_derive::encoding::delta_of_delta")]
+    timestamps: Vec<u8>,
+    #[serde(with = "ya_redis_derive::encoding::rle")]
+    levels: Vec<i8>,
+}
+
+#[test]
+fn integer_sequences_out_of_range() {
+    let narrow = |ids: Vec<i64>, timestamps: Vec<u64>| {
+        let v = Encoded {
+            ids,
+            timestamps,
+            levels: vec![-1],
+        };
+        Narrow::from_redis_value(&Value::Data(v.to_redis_args().pop().unwrap()))
+    };
+    assert_eq!(
+        narrow(vec![i16::MIN as i64, -1], vec![255]).unwrap(),
+        Narrow {
+            ids: vec![i16::MIN, -1],
+            timestamps: vec![255],
+            levels: vec![-1],
+        }
+    );
+    assert!(narrow(vec![i16::MAX as i64 + 1], vec![]).is_err());
+    assert!(narrow(vec![], vec![256]).is_err());
+}
+
+#[test]
+fn rle_max_len() {
+    use ya_redis_derive::{encoding::rle::MAX_LEN, Error, Options};
+
+    let mut v = Encoded {
+        ids: vec![],
+        timestamps: vec![],
+        levels: vec![1; MAX_LEN],
+    };
+    let bytes = Options::new().try_to_bytes(&v).unwrap();
+    assert_eq!(Options::new().from_bytes::<Encoded>(&bytes).unwrap(), v);
+
+    // longer sequences could not be read back, so they are not written either
+    v.levels.push(1);
+    assert_eq!(
+        Options::new().try_to_bytes(&v),
+        Err(Error::Custom(
+            "run-length encoded sequence is too long".into()
+        ))
+    );
+    assert!(Options::new().to_slice(&v, &mut [0; 64]).is_err());
+}
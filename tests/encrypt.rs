@@ -0,0 +1,175 @@
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use ya_binary_format::KeyProvider;
+use ya_redis_derive::Redis;
+
+/// Keys 1 and 2, the current one is switchable.
+struct Keys {
+    current: AtomicU32,
+}
+
+impl KeyProvider for Keys {
+    fn current_key(&self) -> (u32, [u8; 32]) {
+        let id = self.current.load(Ordering::SeqCst);
+        (id, self.key(id).unwrap())
+    }
+
+    fn key(&self, id: u32) -> Option<[u8; 32]> {
+        (1..=2).contains(&id).then_some([id as u8; 32])
+    }
+}
+
+static KEYS: Keys = Keys {
+    current: AtomicU32::new(1),
+};
+
+struct OtherKeys;
+
+impl KeyProvider for OtherKeys {
+    fn current_key(&self) -> (u32, [u8; 32]) {
+        (1, [0; 32])
+    }
+
+    fn key(&self, _id: u32) -> Option<[u8; 32]> {
+        Some([0; 32])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(key_provider = KEYS, dictionary, packed_flags)]
+struct User {
+    id: i64,
+    name: String,
+    #[redis(encrypt)]
+    email: String,
+    #[redis(encrypt)]
+    phone: Option<String>,
+    nickname: String,
+    verified: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(encrypt, key_provider = KEYS, checksum)]
+struct Secret(Vec<User>);
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(encrypt, key_provider = OtherKeys)]
+struct OtherSecret(Vec<User>);
+
+fn user() -> User {
+    User {
+        id: 1,
+        name: String::from("名無しの権兵衛"),
+        email: String::from("nanashi@example.com"),
+        phone: Some(String::from("nanashi@example.com")),
+        nickname: String::from("nanashi@example.com"),
+        verified: true,
+    }
+}
+
+fn contains(haystack: &[u8], needle: &str) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|w| w == needle.as_bytes())
+}
+
+#[test]
+fn encrypt_fields() {
+    let u = user();
+    let bytes = u.to_redis_args().pop().unwrap();
+    assert!(contains(&bytes, "名無しの権兵衛"));
+    // the nickname is not shared with the encrypted fields through the dictionary
+    assert!(contains(&bytes, "nanashi@example.com"));
+    assert_eq!(
+        bytes
+            .windows(u.email.len())
+            .filter(|w| *w == u.email.as_bytes())
+            .count(),
+        1
+    );
+    assert_eq!(User::from_redis_value(&Value::Data(bytes)).unwrap(), u);
+
+    let u = User { phone: None, ..u };
+    let bytes = u.to_redis_args().pop().unwrap();
+    assert_eq!(User::from_redis_value(&Value::Data(bytes)).unwrap(), u);
+}
+
+#[test]
+fn encrypt_value() {
+    let s = Secret(vec![user(); 3]);
+    let bytes = s.to_redis_args().pop().unwrap();
+    assert!(!contains(&bytes, "名無しの権兵衛"));
+    assert_eq!(
+        Secret::from_redis_value(&Value::Data(bytes.clone())).unwrap(),
+        s
+    );
+    // nonces are random
+    assert_ne!(s.to_redis_args().pop().unwrap(), bytes);
+    // wrong key
+    assert!(OtherSecret::from_redis_value(&Value::Data(bytes.clone())).is_err());
+
+    let s = OtherSecret(vec![user(); 3]);
+    let mut bytes = s.to_redis_args().pop().unwrap();
+    bytes[20] ^= 1;
+    assert!(OtherSecret::from_redis_value(&Value::Data(bytes)).is_err());
+}
+
+#[test]
+fn key_rotation() {
+    let u = user();
+    let old = u.to_redis_args().pop().unwrap();
+    KEYS.current.store(2, Ordering::SeqCst);
+    let new = u.to_redis_args().pop().unwrap();
+    KEYS.current.store(1, Ordering::SeqCst);
+    assert_eq!(User::from_redis_value(&Value::Data(old)).unwrap(), u);
+    assert_eq!(User::from_redis_value(&Value::Data(new)).unwrap(), u);
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(key_provider = KEYS)]
+#[serde(rename = "Account", rename_all = "camelCase")]
+struct Renamed {
+    id: i64,
+    #[redis(encrypt)]
+    email_address: String,
+    #[redis(encrypt)]
+    #[serde(rename = "tel")]
+    phone: String,
+}
+
+#[test]
+fn encrypt_renamed_fields() {
+    let r = Renamed {
+        id: 1,
+        email_address: String::from("renamed@example.com"),
+        phone: String::from("+81-90-0000-0000"),
+    };
+    let bytes = r.to_redis_args().pop().unwrap();
+    assert!(!contains(&bytes, "renamed@example.com"));
+    assert!(!contains(&bytes, "+81-90-0000-0000"));
+    assert_eq!(Renamed::from_redis_value(&Value::Data(bytes)).unwrap(), r);
+}
+
+/// Only the options of the stored type apply, so a nested `User` is protected by encrypting the
+/// whole field.
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(key_provider = KEYS)]
+struct Team {
+    name: String,
+    #[redis(encrypt)]
+    owner: User,
+}
+
+#[test]
+fn encrypt_nested() {
+    let t = Team {
+        name: String::from("team"),
+        owner: user(),
+    };
+    let bytes = t.to_redis_args().pop().unwrap();
+    assert!(contains(&bytes, "team"));
+    assert!(!contains(&bytes, "名無しの権兵衛"));
+    assert!(!contains(&bytes, "nanashi@example.com"));
+    assert_eq!(Team::from_redis_value(&Value::Data(bytes)).unwrap(), t);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::MockRedis;
+use redis::Commands;
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::{to_bytes, FixedSize, Redis};
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(fixed_layout)]
+struct Position {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(fixed_layout)]
+struct Player {
+    id: u64,
+    alive: bool,
+    position: Position,
+    inventory: [u16; 4],
+    #[serde(skip)]
+    cache: Option<String>,
+    score: i32,
+}
+
+fn player() -> Player {
+    Player {
+        id: 7,
+        alive: true,
+        position: Position { x: 1.5, y: -2.0 },
+        inventory: [1, 2, 3, 4],
+        cache: None,
+        score: -100,
+    }
+}
+
+#[test]
+fn offsets() {
+    assert_eq!(Position::SIZE, 8);
+    assert_eq!(Player::ID_OFFSET, 0);
+    assert_eq!(Player::ALIVE_OFFSET, 8);
+    assert_eq!(Player::POSITION_OFFSET, 9);
+    assert_eq!(Player::INVENTORY_OFFSET, 17);
+    assert_eq!(Player::SCORE_OFFSET, 25);
+    assert_eq!(Player::SIZE, 29);
+
+    let b = to_bytes(&player());
+    assert_eq!(b.len(), Player::SIZE);
+    assert_eq!(
+        &b[Player::POSITION_OFFSET..][..Position::SIZE],
+        to_bytes(&player().position)
+    );
+    assert_eq!(&b[Player::SCORE_OFFSET..], (-100i32).to_le_bytes());
+}
+
+#[test]
+fn get_and_set_fields() {
+    let mut con = MockRedis::new();
+    let _: () = con.set("p", player()).unwrap();
+
+    assert_eq!(Player::get_id(&mut con, "p").unwrap(), 7);
+    assert_eq!(
+        Player::get_position(&mut con, "p").unwrap(),
+        player().position
+    );
+    assert_eq!(Player::get_score(&mut con, "p").unwrap(), -100);
+
+    Player::set_alive(&mut con, "p", &false).unwrap();
+    Player::set_inventory(&mut con, "p", &[9, 9, 9, 9]).unwrap();
+    assert_eq!(
+        con.log.last().unwrap()[..3],
+        [b"SETRANGE".to_vec(), b"p".to_vec(), b"17".to_vec()]
+    );
+    let p: Player = con.get("p").unwrap();
+    assert_eq!(
+        p,
+        Player {
+            alive: false,
+            inventory: [9, 9, 9, 9],
+            ..player()
+        }
+    );
+
+    assert!(Player::get_score(&mut con, "missing").is_err());
+}
@@ -0,0 +1,356 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::MockRedis;
+use redis::{Commands, FromRedisValue, Value};
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::{hash, Options, Redis};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Address {
+    city: String,
+    zip: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(hash, dictionary)]
+struct User {
+    id: u64,
+    name: String,
+    active: bool,
+    score: f64,
+    nickname: Option<String>,
+    address: Address,
+    tags: Vec<String>,
+    #[serde(skip)]
+    cached: Option<u8>,
+}
+
+fn user() -> User {
+    User {
+        id: 7,
+        name: "ann".into(),
+        active: true,
+        score: -0.5,
+        nickname: None,
+        address: Address {
+            city: "Kyoto".into(),
+            zip: Some(6000000),
+        },
+        tags: vec!["a".into(), "b".into()],
+        cached: None,
+    }
+}
+
+#[test]
+fn roundtrip() {
+    let mut con = MockRedis::new();
+    let _: () = redis::cmd("HSET")
+        .arg("user")
+        .arg(user())
+        .query(&mut con)
+        .unwrap();
+    let hash = &con.hashes[&b"user"[..]];
+    let field = |name: &str| hash.get(name.as_bytes()).map(Vec::as_slice);
+    assert_eq!(field("id"), Some(&b"7"[..]));
+    assert_eq!(field("name"), Some(&b"ann"[..]));
+    assert_eq!(field("active"), Some(&b"1"[..]));
+    assert_eq!(field("score"), Some(&b"-0.5"[..]));
+    assert_eq!(field("nickname"), None);
+    assert_eq!(field("cached"), None);
+    let options = Options::new().dictionary(true);
+    assert_eq!(
+        field("address"),
+        Some(&options.to_bytes(&user().address)[..])
+    );
+    assert_eq!(field("tags"), Some(&options.to_bytes(&user().tags)[..]));
+
+    let got: User = con.hgetall("user").unwrap();
+    assert_eq!(got, user());
+
+    let with_nickname = User {
+        nickname: Some("a".into()),
+        cached: Some(1),
+        ..user()
+    };
+    let _: () = redis::cmd("HSET")
+        .arg("user")
+        .arg(&with_nickname)
+        .query(&mut con)
+        .unwrap();
+    let got: User = con.hgetall("user").unwrap();
+    assert_eq!(got.nickname.as_deref(), Some("a"));
+    assert_eq!(got.cached, None);
+}
+
+#[test]
+fn save() {
+    let mut con = MockRedis::new();
+    let with_nickname = User {
+        nickname: Some("a".into()),
+        ..user()
+    };
+    with_nickname.save(&mut con, "user").unwrap();
+    let got: User = con.hgetall("user").unwrap();
+    assert_eq!(got, with_nickname);
+
+    user().save(&mut con, "user").unwrap();
+    assert!(!con.hashes[&b"user"[..]].contains_key(&b"nickname"[..]));
+    let got: User = con.hgetall("user").unwrap();
+    assert_eq!(got, user());
+}
+
+#[test]
+fn written_by_others() {
+    let mut fields = vec![
+        ("id", "12"),
+        ("name", "bob"),
+        ("active", "0"),
+        ("score", "3"),
+    ]
+    .into_iter()
+    .flat_map(|(k, v)| [Value::Data(k.into()), Value::Data(v.into())])
+    .collect::<Vec<_>>();
+    let options = Options::new().dictionary(true);
+    fields.extend([
+        Value::Data(b"address".to_vec()),
+        Value::Data(options.to_bytes(&user().address)),
+        Value::Data(b"tags".to_vec()),
+        Value::Data(options.to_bytes(&Vec::<String>::new())),
+    ]);
+    let got = User::from_redis_value(&Value::Bulk(fields.clone())).unwrap();
+    assert_eq!(got.id, 12);
+    assert!(!got.active);
+    assert_eq!(got.score, 3.0);
+
+    fields[1] = Value::Data(b"twelve".to_vec());
+    let e = User::from_redis_value(&Value::Bulk(fields.clone())).unwrap_err();
+    assert!(e.detail().unwrap().starts_with("id: "), "{:?}", e.detail());
+    let e = User::from_redis_value(&Value::Bulk(fields[2..].to_vec())).unwrap_err();
+    assert_eq!(e.detail(), Some("id: missing field"));
+}
+
+#[test]
+fn fields() {
+    let options = Options::new();
+    assert_eq!(
+        hash::encode_field(&options, &Some(-3i8)),
+        Some(b"-3".to_vec())
+    );
+    assert_eq!(hash::encode_field(&options, &None::<i8>), None);
+    assert_eq!(hash::encode_field(&options, &'é'), Some("é".into()));
+    let map = HashMap::from([(1u8, 2u8)]);
+    let b = hash::encode_field(&options, &map).unwrap();
+    assert_eq!(b, options.to_bytes(&map));
+    assert_eq!(
+        hash::decode_field::<HashMap<u8, u8>>(&options, Some(&b)).unwrap(),
+        map
+    );
+    assert_eq!(
+        hash::decode_field::<Option<u64>>(&options, Some(b"18446744073709551615")).unwrap(),
+        Some(u64::MAX)
+    );
+    assert_eq!(
+        hash::decode_field::<Option<u64>>(&options, None).unwrap(),
+        None
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum Shape {
+    Point,
+    Circle { radius: f64 },
+    Rect(u32, u32),
+    Label(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Inner {
+    b: Option<i32>,
+    c: (u8, Option<bool>, String),
+    d: Vec<Option<u16>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(hash)]
+struct Flat {
+    #[redis(flatten)]
+    a: Inner,
+    #[redis(flatten)]
+    shapes: Vec<Shape>,
+    #[redis(flatten)]
+    counts: HashMap<String, i64>,
+    #[redis(flatten)]
+    maybe: Option<Inner>,
+    #[redis(flatten)]
+    unit: (),
+    kept: Inner,
+}
+
+fn flat() -> Flat {
+    let inner = Inner {
+        b: None,
+        c: (1, Some(false), "x.y".into()),
+        d: vec![Some(3), None, Some(5)],
+    };
+    Flat {
+        a: inner.clone(),
+        shapes: vec![
+            Shape::Point,
+            Shape::Circle { radius: 1.5 },
+            Shape::Rect(2, 3),
+            Shape::Label("l".into()),
+        ],
+        counts: HashMap::from([("views".into(), 10), ("likes".into(), -1)]),
+        maybe: None,
+        unit: (),
+        kept: inner,
+    }
+}
+
+#[test]
+fn flatten() {
+    let mut con = MockRedis::new();
+    let _: () = redis::cmd("HSET")
+        .arg("flat")
+        .arg(flat())
+        .query(&mut con)
+        .unwrap();
+    let mut names = con.hashes[&b"flat"[..]]
+        .iter()
+        .filter(|(name, _)| *name != b"kept")
+        .map(|(name, v)| {
+            format!(
+                "{}={}",
+                String::from_utf8_lossy(name),
+                String::from_utf8_lossy(v)
+            )
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "a.c.0=1",
+            "a.c.1=0",
+            "a.c.2=x.y",
+            "a.d.0=3",
+            "a.d.2=5",
+            "counts.likes=-1",
+            "counts.views=10",
+            "shapes.0=Point",
+            "shapes.1.Circle.radius=1.5",
+            "shapes.2.Rect.0=2",
+            "shapes.2.Rect.1=3",
+            "shapes.3.Label=l",
+            "unit=",
+        ]
+    );
+    let got: Flat = con.hgetall("flat").unwrap();
+    assert_eq!(got, flat());
+
+    let _: () = con.hset("flat", "a.b", 4).unwrap();
+    let _: () = con.hset("flat", "maybe.c.0", 9).unwrap();
+    let _: () = con.hset("flat", "maybe.c.2", "").unwrap();
+    let got: Flat = con.hgetall("flat").unwrap();
+    assert_eq!(got.a.b, Some(4));
+    assert_eq!(
+        got.maybe,
+        Some(Inner {
+            b: None,
+            c: (9, None, String::new()),
+            d: vec![],
+        })
+    );
+
+    let _: () = con.hset("flat", "shapes.0", "Square").unwrap();
+    let e = Flat::from_redis_value(&Value::Bulk(
+        con.hashes[&b"flat"[..]]
+            .iter()
+            .flat_map(|(k, v)| [Value::Data(k.clone()), Value::Data(v.clone())])
+            .collect(),
+    ))
+    .unwrap_err();
+    assert!(
+        e.detail().unwrap().starts_with("shapes: "),
+        "{:?}",
+        e.detail()
+    );
+}
+
+#[test]
+fn flatten_rewritten() {
+    let mut con = MockRedis::new();
+    flat().save(&mut con, "flat").unwrap();
+    // a shorter sequence, whose second item changes variant
+    let rewritten = Flat {
+        shapes: vec![Shape::Point, Shape::Rect(4, 5)],
+        counts: HashMap::from([("a.b".into(), 1), ("c\\d".into(), 2)]),
+        ..flat()
+    };
+    rewritten.save(&mut con, "flat").unwrap();
+    let hash = &con.hashes[&b"flat"[..]];
+    assert!(hash.contains_key(&b"counts.a\\.b"[..]));
+    assert!(hash.contains_key(&b"counts.c\\\\d"[..]));
+    assert!(!hash.contains_key(&b"shapes.1.Circle.radius"[..]));
+    let got: Flat = con.hgetall("flat").unwrap();
+    assert_eq!(got, rewritten);
+
+    let _: () = con.hset("flat", "shapes.4000000000", "Point").unwrap();
+    let e = con.hgetall::<_, Flat>("flat").unwrap_err();
+    assert!(
+        e.detail().unwrap().contains("more than"),
+        "{:?}",
+        e.detail()
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(hash)]
+struct Stats {
+    #[redis(counter)]
+    views: i64,
+    #[redis(counter)]
+    rating: f64,
+    owner: Option<String>,
+    history: Vec<u32>,
+}
+
+#[test]
+fn accessors() {
+    let mut con = MockRedis::new();
+    let stats = Stats {
+        views: 1,
+        rating: 0.5,
+        owner: Some("ann".into()),
+        history: vec![1, 2],
+    };
+    let _: () = redis::cmd("HSET")
+        .arg("stats")
+        .arg(&stats)
+        .query(&mut con)
+        .unwrap();
+    assert_eq!(Stats::get_views(&mut con, "stats").unwrap(), 1);
+    assert_eq!(Stats::get_history(&mut con, "stats").unwrap(), [1, 2]);
+    assert_eq!(Stats::incr_views(&mut con, "stats", 41).unwrap(), 42);
+    assert_eq!(Stats::incr_rating(&mut con, "stats", 0.25).unwrap(), 0.75);
+    assert_eq!(Stats::incr_views(&mut con, "other", -1).unwrap(), -1);
+
+    Stats::set_history(&mut con, "stats", &vec![3]).unwrap();
+    Stats::set_owner(&mut con, "stats", &None).unwrap();
+    assert_eq!(Stats::get_owner(&mut con, "stats").unwrap(), None);
+    let got: Stats = con.hgetall("stats").unwrap();
+    assert_eq!(
+        got,
+        Stats {
+            views: 42,
+            rating: 0.75,
+            owner: None,
+            history: vec![3],
+        }
+    );
+
+    let e = Stats::get_views(&mut con, "missing").unwrap_err();
+    assert_eq!(e.detail(), Some("views: missing field"));
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::MockRedis;
+use redis::{Commands, ToRedisArgs};
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::{indexed, Error, Options, Redis};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct A {
+    id: i64,
+    name: String,
+    score: u64,
+}
+
+#[derive(Debug, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(indexed, dictionary, checksum)]
+struct V(Vec<A>);
+
+fn items(n: usize) -> Vec<A> {
+    (0..n)
+        .map(|i| A {
+            id: i as i64,
+            name: format!("item {}", i),
+            score: i as u64 * 10,
+        })
+        .collect()
+}
+
+#[test]
+fn roundtrip() {
+    let mut con = MockRedis::new();
+    let _: () = con.set("v", V(items(100))).unwrap();
+    let v: V = con.get("v").unwrap();
+    assert_eq!(v, V(items(100)));
+    let _: () = con.set("empty", V(vec![])).unwrap();
+    let v: V = con.get("empty").unwrap();
+    assert_eq!(v, V(vec![]));
+}
+
+#[test]
+fn get_range() {
+    let mut con = MockRedis::new();
+    let _: () = con.set("v", V(items(1000))).unwrap();
+    assert_eq!(V::get_len(&mut con, "v").unwrap(), 1000);
+
+    con.log.clear();
+    assert_eq!(
+        V::get_range(&mut con, "v", 10..13).unwrap(),
+        items(1000)[10..13]
+    );
+    let getranges: Vec<_> = con
+        .log
+        .iter()
+        .filter(|args| args[0] == b"GETRANGE")
+        .collect();
+    assert_eq!(getranges.len(), 3);
+    let fetched: usize = getranges
+        .iter()
+        .map(|args| {
+            let n = |i: usize| {
+                std::str::from_utf8(&args[i])
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap()
+            };
+            n(3) - n(2) + 1
+        })
+        .sum();
+    assert!(fetched < 150, "{}", fetched);
+
+    assert_eq!(
+        V::get_range(&mut con, "v", 998..2000).unwrap(),
+        items(1000)[998..]
+    );
+    assert_eq!(V::get_range(&mut con, "v", 2000..3000).unwrap(), []);
+    assert_eq!(V::get_range(&mut con, "v", 5..5).unwrap(), []);
+    assert_eq!(V::get_len(&mut con, "missing").unwrap(), 0);
+    assert_eq!(V::get_range(&mut con, "missing", 0..10).unwrap(), []);
+}
+
+#[test]
+fn get_range_written_meanwhile() {
+    let mut con = MockRedis::new();
+    let _: () = con.set("v", V(items(10))).unwrap();
+    // another client replaces the value between the reads of the index and of the items
+    let other = V(items(20)[5..].to_vec());
+    con.before_exec = vec![[&b"SET"[..], b"v"]
+        .iter()
+        .map(|arg| arg.to_vec())
+        .chain(other.to_redis_args())
+        .collect()];
+    con.log.clear();
+    assert_eq!(V::get_range(&mut con, "v", 0..3).unwrap(), items(20)[5..8]);
+    assert_eq!(con.log.iter().filter(|args| args[0] == b"EXEC").count(), 2);
+}
+
+#[test]
+fn corrupted() {
+    let options = Options::new().checksum(true);
+    let mut buf = indexed::to_bytes(&options, &items(3));
+    assert_eq!(
+        indexed::from_bytes::<A>(&options, &buf[..buf.len() - 1]),
+        Err(Error::Truncated)
+    );
+    assert_eq!(
+        indexed::from_bytes::<A>(&options, &[&buf[..], &[0]].concat()),
+        Err(Error::TrailingBytes(1))
+    );
+    // the end of the first item after that of the second
+    buf[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(
+        indexed::from_bytes::<A>(&options, &buf),
+        Err(Error::InvalidLength(_))
+    ));
+    buf[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(
+        indexed::from_bytes::<A>(&options, &buf),
+        Err(Error::Truncated)
+    );
+
+    let mut buf = indexed::to_bytes(&options, &items(3));
+    let n = buf.len();
+    buf[n - 1] ^= 1;
+    assert!(matches!(
+        indexed::from_bytes::<A>(&options, &buf),
+        Err(Error::ChecksumMismatch { .. })
+    ));
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::Redis;
+
+#[derive(Debug, Redis, Deserialize, Serialize)]
+#[redis(key = "user:{id}")]
+struct User {
+    id: i64,
+    name: String,
+}
+
+#[derive(Debug, Redis, Deserialize, Serialize)]
+#[redis(hash, key = "{{tenant:{tenant}}}:session:{id}:{tenant}")]
+struct Session {
+    tenant: String,
+    id: u32,
+    user: i64,
+}
+
+#[test]
+fn keys() {
+    let user = User {
+        id: 42,
+        name: "ann".into(),
+    };
+    assert_eq!(user.redis_key(), "user:42");
+    assert_eq!(User::key_for(&-1), "user:-1");
+    assert_eq!(user.name, "ann");
+
+    let session = Session {
+        tenant: "acme".into(),
+        id: 7,
+        user: 42,
+    };
+    assert_eq!(session.redis_key(), "{tenant:acme}:session:7:acme");
+    assert_eq!(
+        Session::key_for(&"x".to_owned(), &1),
+        "{tenant:x}:session:1:x"
+    );
+    assert_eq!(session.user, 42);
+
+    // like a hand-written `format!`
+    let session = Session {
+        tenant: "a:b".into(),
+        ..session
+    };
+    assert_eq!(
+        session.redis_key(),
+        format!("{{tenant:{}}}:session:{}:{}", "a:b", 7, "a:b")
+    );
+}
+
+#[derive(Debug, Redis, Deserialize, Serialize)]
+#[redis(key = "file:{owner}:{path}", key_escape)]
+struct File {
+    owner: u32,
+    path: String,
+}
+
+#[test]
+fn escaped_keys() {
+    let file = File {
+        owner: 1,
+        path: "a:b\\".into(),
+    };
+    assert_eq!(file.redis_key(), "file:1:a\\:b\\\\");
+    assert_ne!(
+        File::key_for(&1, &"2:c".to_owned()),
+        File::key_for(&12, &"c".to_owned())
+    );
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::MockRedis;
+use redis::{Commands, FromRedisValue, Value};
+use serde::{Deserialize, Serialize};
+use ya_redis_derive::{codec::Json, Options, Redis};
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(dictionary, checksum, fallback = Json)]
+struct Profile {
+    id: u64,
+    name: String,
+    tags: Vec<String>,
+}
+
+fn profile() -> Profile {
+    Profile {
+        id: 42,
+        name: "ann".into(),
+        tags: vec!["a".into(), "b".into()],
+    }
+}
+
+fn binary(profile: &Profile) -> Vec<u8> {
+    Options::new()
+        .dictionary(true)
+        .checksum(true)
+        .to_bytes(profile)
+}
+
+#[test]
+fn reads_both_formats() {
+    let json = serde_json::to_vec(&profile()).unwrap();
+    assert_eq!(
+        Profile::from_redis_value(&Value::Data(json)).unwrap(),
+        profile()
+    );
+    assert_eq!(
+        Profile::from_redis_value(&Value::Data(binary(&profile()))).unwrap(),
+        profile()
+    );
+    // the error of the current format
+    let e = Profile::from_redis_value(&Value::Data(b"\x01".to_vec())).unwrap_err();
+    assert_eq!(e.kind(), redis::ErrorKind::TypeError);
+    assert!(!e.detail().unwrap().contains("JSON"), "{:?}", e.detail());
+}
+
+#[test]
+fn get_migrating() {
+    let mut con = MockRedis::new();
+    con.strings
+        .insert(b"p".to_vec(), serde_json::to_vec(&profile()).unwrap());
+    assert_eq!(
+        Profile::get_migrating(&mut con, "p").unwrap(),
+        Some(profile())
+    );
+    assert_eq!(con.strings[&b"p"[..]], binary(&profile()));
+    assert_eq!(con.log.last().unwrap()[0], b"EVAL");
+
+    con.log.clear();
+    assert_eq!(
+        Profile::get_migrating(&mut con, "p").unwrap(),
+        Some(profile())
+    );
+    assert_eq!(
+        con.log.len(),
+        1,
+        "values in the current format are not written"
+    );
+    assert_eq!(Profile::get_migrating(&mut con, "missing").unwrap(), None);
+
+    let _: () = con.set("p", profile()).unwrap();
+    let got: Profile = con.get("p").unwrap();
+    assert_eq!(got, profile());
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(checksum, fallback = Json)]
+struct Counter(u64);
+
+#[test]
+fn json_which_is_binary_length() {
+    let json = b"10000000".to_vec();
+    assert_eq!(json.len(), 8, "as long as a `u64`");
+    assert_eq!(
+        Counter::from_redis_value(&Value::Data(json.clone())).unwrap(),
+        Counter(10000000)
+    );
+
+    let mut con = MockRedis::new();
+    con.strings.insert(b"c".to_vec(), json);
+    assert_eq!(
+        Counter::get_migrating(&mut con, "c").unwrap(),
+        Some(Counter(10000000))
+    );
+    assert_eq!(
+        con.strings[&b"c"[..]],
+        Options::new().checksum(true).to_bytes(&Counter(10000000))
+    );
+}
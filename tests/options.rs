@@ -0,0 +1,183 @@
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug};
+use ya_redis_derive::{Error, Options, Redis};
+
+fn do_test<T: FromRedisValue + ToRedisArgs + PartialEq + Debug>(v: T) -> usize {
+    let mut args = v.to_redis_args();
+    assert_eq!(args.len(), 1);
+    let bytes = args.pop().unwrap();
+    let len = bytes.len();
+    let v2 = T::from_redis_value(&Value::Data(bytes)).unwrap();
+    assert_eq!(v, v2);
+    len
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+struct A {
+    id: i64,
+    name: String,
+    description: Option<String>,
+    tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(dictionary)]
+struct Dictionary(Vec<A>);
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+struct Plain(Vec<A>);
+
+fn gen_a() -> Vec<A> {
+    let names = ["abcdefg", "名無しの権兵衛", "最高にイケてる名前", ""];
+    (0..100)
+        .map(|i| A {
+            id: i,
+            name: names[i as usize % names.len()].to_string(),
+            description: (i % 3 != 0).then(|| names[i as usize % 2].repeat(5)),
+            tags: (0..i % 4)
+                .map(|j| (format!("tag{}", j), names[j as usize].to_string()))
+                .collect(),
+        })
+        .collect()
+}
+
+#[test]
+fn dictionary() {
+    let a = gen_a();
+    let dictionary = do_test(Dictionary(a.clone()));
+    let plain = do_test(Plain(a));
+    assert!(dictionary * 3 < plain);
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+struct Flags {
+    a: bool,
+    b: Option<u8>,
+    c: Option<String>,
+    d: bool,
+    e: Vec<bool>,
+    f: Option<Box<Flags>>,
+    g: (bool, Option<()>),
+    h: bool,
+    i: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(packed_flags)]
+struct PackedFlags(Vec<Flags>);
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+struct PlainFlags(Vec<Flags>);
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(packed_flags, dictionary)]
+struct PackedFlagsDictionary(Vec<Flags>);
+
+#[test]
+fn packed_flags() {
+    let flags = Flags {
+        a: true,
+        b: None,
+        c: Some(String::from("abc")),
+        d: false,
+        e: vec![true, false, true],
+        f: Some(Box::new(Flags {
+            a: false,
+            b: Some(1),
+            c: None,
+            d: true,
+            e: vec![],
+            f: None,
+            g: (false, None),
+            h: true,
+            i: Some(false),
+        })),
+        g: (true, Some(())),
+        h: false,
+        i: None,
+    };
+    let v = vec![flags.clone(); 10];
+    let packed = do_test(PackedFlags(v.clone()));
+    assert!(packed < do_test(PlainFlags(v.clone())));
+    do_test(PackedFlagsDictionary(v));
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(compress, compress_above = 256)]
+struct Compressed(Vec<A>);
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(compress, dictionary, packed_flags)]
+struct CompressedAll(Vec<A>);
+
+#[test]
+fn compress() {
+    let a = gen_a();
+    let plain = do_test(Plain(a.clone()));
+    let compressed = do_test(Compressed(a.clone()));
+    assert!(compressed * 2 < plain);
+    do_test(CompressedAll(a.clone()));
+    // not compressed but still has the header
+    assert_eq!(
+        do_test(Compressed(a[..1].to_vec())),
+        do_test(Plain(a[..1].to_vec())) + 1
+    );
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(checksum)]
+struct Checksum(Vec<A>);
+
+#[derive(Debug, Clone, PartialEq, Redis, Deserialize, Serialize)]
+#[redis(checksum, compress, compress_above = 16)]
+struct ChecksumCompressed(Vec<A>);
+
+#[test]
+fn checksum() {
+    let options = Options::new().checksum(true);
+    assert_eq!(options.to_bytes(&()), [0, 0, 0, 0]);
+    assert_eq!(
+        options.to_bytes(b"123456789"),
+        [b"123456789".as_slice(), &0xe306_9283u32.to_le_bytes()].concat()
+    );
+
+    let a = gen_a();
+    assert_eq!(do_test(Checksum(a.clone())), do_test(Plain(a.clone())) + 4);
+    do_test(ChecksumCompressed(a.clone()));
+
+    let mut bytes = Checksum(a.clone()).to_redis_args().pop().unwrap();
+    bytes[10] ^= 1;
+    assert!(matches!(
+        options.from_bytes::<Vec<A>>(&bytes),
+        Err(Error::ChecksumMismatch { .. })
+    ));
+    assert!(Checksum::from_redis_value(&Value::Data(bytes)).is_err());
+
+    let bytes = ChecksumCompressed(a).to_redis_args().pop().unwrap();
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(ChecksumCompressed::from_redis_value(&Value::Data(truncated.to_vec())).is_err());
+    assert_eq!(options.from_bytes::<()>(&[0; 3]), Err(Error::Truncated));
+}
+
+#[test]
+fn to_slice() {
+    let a = gen_a();
+    let mut buf = vec![0; 1 << 16];
+    for options in [
+        Options::new(),
+        Options::new().checksum(true),
+        Options::new().dictionary(true).packed_flags(true),
+        Options::new().compress(true).checksum(true),
+    ] {
+        let bytes = options.to_bytes(&a);
+        let n = options.to_slice(&a, &mut buf).unwrap();
+        assert_eq!(&buf[..n], bytes);
+        assert_eq!(
+            options.to_slice(&a, &mut buf[..bytes.len() - 1]),
+            Err(Error::BufferFull)
+        );
+    }
+    let n = ya_binary_format::to_slice(&(1u8, 2u16), &mut buf).unwrap();
+    assert_eq!(&buf[..n], [1, 2, 0]);
+}
@@ -0,0 +1,95 @@
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use ya_redis_derive::RedisKey;
+
+#[derive(Debug, Clone, PartialEq, RedisKey)]
+struct UserKey {
+    tenant: u32,
+    id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, RedisKey)]
+#[redis(prefix = "app:doc")]
+struct DocKey(String, u8);
+
+#[derive(Debug, Clone, PartialEq, RedisKey)]
+#[redis(prefix = "")]
+struct Pair(String, String);
+
+#[test]
+fn format() {
+    let key = UserKey { tenant: 7, id: -3 };
+    assert_eq!(key.to_string(), "user:7:-3");
+    assert_eq!(key.to_redis_args(), [b"user:7:-3".to_vec()]);
+    assert_eq!(
+        DocKey("a:b\\c".into(), 1).to_string(),
+        "app:doc:a\\:b\\\\c:1"
+    );
+    assert_eq!(Pair("a".into(), "".into()).to_string(), "a:");
+}
+
+#[test]
+fn roundtrip() {
+    let key = UserKey { tenant: 7, id: -3 };
+    assert_eq!("user:7:-3".parse::<UserKey>().unwrap(), key);
+    for s in ["", ":", "\\", "a:b\\c", "\\:", "::"] {
+        let doc = DocKey(s.into(), 2);
+        assert_eq!(doc.to_string().parse::<DocKey>().unwrap(), doc);
+        let pair = Pair(s.into(), s.into());
+        assert_eq!(pair.to_string().parse::<Pair>().unwrap(), pair);
+    }
+}
+
+#[test]
+fn scan_results() {
+    let keys: Vec<UserKey> = FromRedisValue::from_redis_value(&Value::Bulk(vec![
+        Value::Data(b"user:1:10".to_vec()),
+        Value::Data(b"user:2:20".to_vec()),
+    ]))
+    .unwrap();
+    assert_eq!(
+        keys,
+        [UserKey { tenant: 1, id: 10 }, UserKey { tenant: 2, id: 20 }]
+    );
+    assert_eq!(
+        DocKey::from_redis_value(&Value::Status("app:doc:x\\:y:3".into())).unwrap(),
+        DocKey("x:y".into(), 3)
+    );
+}
+
+#[test]
+fn invalid() {
+    let err = |s: &str| s.parse::<UserKey>().unwrap_err().to_string();
+    assert_eq!(
+        err("order:1:2"),
+        "invalid key \"order:1:2\": expected the prefix \"user\""
+    );
+    assert_eq!(
+        err("user:1"),
+        "invalid key \"user:1\": expected 2 fields, found 1"
+    );
+    assert_eq!(
+        err("user:1:2:3"),
+        "invalid key \"user:1:2:3\": expected 2 fields, found 3"
+    );
+    assert_eq!(
+        err("user:x:2"),
+        "invalid key \"user:x:2\": invalid `tenant`"
+    );
+    assert_eq!(
+        err("user:1:2\\"),
+        "invalid key \"user:1:2\\\\\": invalid escape"
+    );
+    assert_eq!(
+        "app:doc:a:b:1".parse::<DocKey>().unwrap_err().to_string(),
+        "invalid key \"app:doc:a:b:1\": expected 2 fields, found 3"
+    );
+
+    let e = UserKey::from_redis_value(&Value::Data(b"user:1".to_vec())).unwrap_err();
+    assert_eq!(e.kind(), redis::ErrorKind::TypeError);
+    assert_eq!(
+        e.detail(),
+        Some("invalid key \"user:1\": expected 2 fields, found 1")
+    );
+    assert!(UserKey::from_redis_value(&Value::Int(1)).is_err());
+    assert!(UserKey::from_redis_value(&Value::Data(vec![0xff])).is_err());
+}
@@ -0,0 +1,116 @@
+use redis::{FromRedisValue, Value};
+use ya_redis_derive::Redis;
+
+fn data(s: &str) -> Value {
+    Value::Data(s.as_bytes().to_vec())
+}
+
+#[derive(Debug, PartialEq, Redis)]
+#[redis(reply = "array")]
+struct Fetched {
+    name: String,
+    age: Option<u32>,
+    score: f64,
+}
+
+#[derive(Debug, PartialEq, Redis)]
+#[redis(reply = "array")]
+struct Pair(i64, Vec<String>);
+
+#[test]
+fn array() {
+    let v = Value::Bulk(vec![data("ann"), Value::Nil, data("1.5")]);
+    assert_eq!(
+        Fetched::from_redis_value(&v).unwrap(),
+        Fetched {
+            name: "ann".into(),
+            age: None,
+            score: 1.5,
+        }
+    );
+    let v = Value::Bulk(vec![
+        Value::Int(3),
+        Value::Bulk(vec![data("a"), data("b")]),
+        data("extra"),
+    ]);
+    assert_eq!(
+        Pair::from_redis_value(&v).unwrap(),
+        Pair(3, vec!["a".into(), "b".into()])
+    );
+
+    let e = Fetched::from_redis_value(&Value::Bulk(vec![data("ann")])).unwrap_err();
+    assert!(
+        e.detail().unwrap().starts_with("score: "),
+        "{:?}",
+        e.detail()
+    );
+    assert!(Fetched::from_redis_value(&data("ann")).is_err());
+    assert_eq!(
+        Option::<Fetched>::from_redis_value(&Value::Nil).unwrap(),
+        None
+    );
+}
+
+#[derive(Debug, PartialEq, Redis)]
+#[redis(reply = "map", rename_all = "kebab-case")]
+struct StreamInfo {
+    length: u64,
+    last_generated_id: String,
+    #[redis(rename = "groups")]
+    group_count: u32,
+    first_entry: Option<Vec<Value>>,
+}
+
+#[derive(Debug, PartialEq, Redis)]
+#[redis(reply = "map")]
+struct MemoryStats {
+    #[redis(rename = "peak.allocated")]
+    peak_allocated: i64,
+    #[redis(rename = "keys.count")]
+    keys_count: i64,
+}
+
+#[test]
+fn map() {
+    let v = Value::Bulk(vec![
+        data("length"),
+        Value::Int(2),
+        Value::Status("last-generated-id".into()),
+        data("1-0"),
+        data("radix-tree-keys"),
+        Value::Int(1),
+        data("groups"),
+        Value::Int(0),
+    ]);
+    assert_eq!(
+        StreamInfo::from_redis_value(&v).unwrap(),
+        StreamInfo {
+            length: 2,
+            last_generated_id: "1-0".into(),
+            group_count: 0,
+            first_entry: None,
+        }
+    );
+    let v = Value::Bulk(vec![
+        data("peak.allocated"),
+        Value::Int(1024),
+        data("keys.count"),
+        Value::Int(7),
+    ]);
+    assert_eq!(
+        MemoryStats::from_redis_value(&v).unwrap(),
+        MemoryStats {
+            peak_allocated: 1024,
+            keys_count: 7,
+        }
+    );
+
+    let e = MemoryStats::from_redis_value(&Value::Bulk(vec![data("keys.count")])).unwrap_err();
+    assert_eq!(e.kind(), redis::ErrorKind::TypeError);
+    let e = MemoryStats::from_redis_value(&Value::Bulk(vec![])).unwrap_err();
+    assert!(
+        e.detail().unwrap().starts_with("peak.allocated: "),
+        "{:?}",
+        e.detail()
+    );
+}
@@ -0,0 +1,251 @@
+use proptest::{collection::vec, prelude::*};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Debug};
+use ya_binary_format::{de::MAX_DEPTH, encoding};
+use ya_redis_derive::{Error, Options};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum Kind {
+    Unit,
+    Newtype(u16),
+    Tuple(bool, String),
+    Struct { a: bool, b: Option<u32> },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Record {
+    id: u64,
+    small: i8,
+    big: i128,
+    ratio: f64,
+    initial: char,
+    name: String,
+    tag: Option<String>,
+    active: bool,
+    kinds: Vec<Kind>,
+    attrs: BTreeMap<String, Vec<u8>>,
+    nested: Vec<Vec<Option<i16>>>,
+    pair: (u8, Option<bool>),
+    #[serde(with = "encoding::delta")]
+    ids: Vec<i64>,
+    #[serde(with = "encoding::rle")]
+    states: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Node {
+    value: i32,
+    next: Option<Box<Node>>,
+}
+
+fn all_options() -> Vec<Options> {
+    let mut options = Vec::new();
+    for bits in 0..16 {
+        options.push(
+            Options::new()
+                .dictionary(bits & 1 != 0)
+                .packed_flags(bits & 2 != 0)
+                .checksum(bits & 4 != 0)
+                .compress_above(if bits & 8 != 0 { 0 } else { usize::MAX }),
+        );
+    }
+    options
+}
+
+fn short_string() -> impl Strategy<Value = String> {
+    // a small alphabet so that the dictionary gets hits
+    "[ab名]{0,3}"
+}
+
+fn kind() -> impl Strategy<Value = Kind> {
+    prop_oneof![
+        Just(Kind::Unit),
+        any::<u16>().prop_map(Kind::Newtype),
+        (any::<bool>(), short_string()).prop_map(|(a, b)| Kind::Tuple(a, b)),
+        (any::<bool>(), any::<Option<u32>>()).prop_map(|(a, b)| Kind::Struct { a, b }),
+    ]
+}
+
+prop_compose! {
+    fn record()(
+        (id, small, big, ratio, initial) in (any::<u64>(), any::<i8>(), any::<i128>(), -1e9..1e9f64, any::<char>()),
+        (name, tag, active) in (short_string(), proptest::option::of(short_string()), any::<bool>()),
+        kinds in vec(kind(), 0..4),
+        attrs in proptest::collection::btree_map(short_string(), vec(any::<u8>(), 0..8), 0..4),
+        nested in vec(vec(any::<Option<i16>>(), 0..4), 0..4),
+        pair in any::<(u8, Option<bool>)>(),
+        ids in vec(any::<i64>(), 0..8),
+        states in vec(0..3u8, 0..32),
+    ) -> Record {
+        Record { id, small, big, ratio, initial, name, tag, active, kinds, attrs, nested, pair, ids, states }
+    }
+}
+
+fn node() -> impl Strategy<Value = Node> {
+    vec(any::<i32>(), 1..16).prop_map(|values| {
+        let mut next = None;
+        for value in values.into_iter().rev() {
+            next = Some(Box::new(Node { value, next }));
+        }
+        *next.unwrap()
+    })
+}
+
+/// Decode as every test type, only checking that it returns.
+fn decode_all(options: &Options, b: &[u8]) {
+    let _ = options.from_bytes::<Record>(b);
+    let _ = options.from_bytes::<Kind>(b);
+    let _ = options.from_bytes::<Vec<Kind>>(b);
+    let _ = options.from_bytes::<Node>(b);
+    let _ = options.from_bytes::<BTreeMap<String, Option<Vec<u8>>>>(b);
+    let _ = options.from_bytes::<Vec<Vec<Option<(bool, char)>>>>(b);
+}
+
+fn roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(v: &T) {
+    for options in all_options() {
+        let buf = options.to_bytes(v);
+        assert_eq!(&options.from_bytes::<T>(&buf).unwrap(), v, "{:?}", options);
+    }
+}
+
+proptest! {
+    #[test]
+    fn roundtrip_record(record in record()) {
+        roundtrip(&record);
+    }
+
+    #[test]
+    fn roundtrip_records(records in vec(record(), 0..4)) {
+        roundtrip(&records);
+    }
+
+    #[test]
+    fn roundtrip_node(node in node()) {
+        roundtrip(&node);
+    }
+
+    #[test]
+    fn garbage(b in vec(any::<u8>(), 0..256)) {
+        for options in all_options() {
+            decode_all(&options, &b);
+        }
+    }
+
+    #[test]
+    fn mutated(record in record(), flips in vec((any::<usize>(), 1..=255u8), 1..4), cut in any::<usize>()) {
+        for options in all_options() {
+            let mut buf = options.to_bytes(&record);
+            for &(i, x) in &flips {
+                let n = buf.len();
+                buf[i % n] ^= x;
+            }
+            decode_all(&options, &buf);
+            buf.truncate(cut % (buf.len() + 1));
+            decode_all(&options, &buf);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Deep(Option<Box<Deep>>);
+
+#[test]
+fn depth_limit() {
+    let mut deep = Deep(None);
+    for _ in 0..MAX_DEPTH / 2 - 1 {
+        deep = Deep(Some(Box::new(deep)));
+    }
+    roundtrip(&deep);
+    let deeper = Deep(Some(Box::new(deep)));
+    let buf = Options::new().to_bytes(&deeper);
+    assert_eq!(Options::new().from_bytes::<Deep>(&buf), Err(Error::TooDeep));
+
+    let buf = vec![b'1'; 1 << 20];
+    assert_eq!(Options::new().from_bytes::<Deep>(&buf), Err(Error::TooDeep));
+}
+
+#[test]
+fn huge_lengths() {
+    let options = Options::new();
+    let mut buf = vec![255];
+    buf.extend_from_slice(&u64::MAX.to_le_bytes());
+    assert!(options.from_bytes::<Vec<u64>>(&buf).is_err());
+    assert!(options.from_bytes::<String>(&buf).is_err());
+    assert!(options.from_bytes::<BTreeMap<u8, u8>>(&buf).is_err());
+
+    let buf = [254, 255, 255, 255, 255, b'a'];
+    assert_eq!(options.from_bytes::<String>(&buf), Err(Error::Truncated));
+    assert_eq!(
+        options.from_bytes::<Vec<String>>(&buf),
+        Err(Error::Truncated)
+    );
+}
+
+#[test]
+fn dictionary_expansion() {
+    let options = Options::new().dictionary(true);
+    // one 64 KiB string followed by 10000 references to it
+    let mut buf = vec![254];
+    buf.extend_from_slice(&(1 + 10_000u32).to_le_bytes());
+    buf.extend_from_slice(&[0, 254]);
+    buf.extend_from_slice(&(1u32 << 16).to_le_bytes());
+    buf.extend_from_slice(&[b'a'; 1 << 16]);
+    buf.extend_from_slice(&[1; 10_000]);
+    assert_eq!(
+        options.from_bytes::<Vec<String>>(&buf),
+        Err(Error::TooLarge)
+    );
+
+    let names = vec!["a".repeat(1024); 100];
+    roundtrip(&names);
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Runs(#[serde(with = "encoding::rle")] Vec<u64>);
+
+#[test]
+fn rle_limit() {
+    let runs = Runs(vec![7; encoding::rle::MAX_LEN]);
+    roundtrip(&runs);
+    // value 0 repeated u64::MAX times
+    let buf = [11, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 1];
+    assert!(Options::new().from_bytes::<Runs>(&buf).is_err());
+}
+
+#[test]
+fn compressed_size_limit() {
+    let options = Options::new().compress(true);
+    let buf = [1, 255, 255, 255, 255, 0];
+    assert_eq!(
+        options.from_bytes::<Vec<u8>>(&buf),
+        Err(Error::InvalidCompressedData)
+    );
+}
+
+#[test]
+fn errors() {
+    let options = Options::new();
+    assert_eq!(options.from_bytes::<u8>(&[]), Err(Error::Truncated));
+    assert_eq!(options.from_bytes::<u32>(&[1, 2]), Err(Error::Truncated));
+    assert_eq!(
+        options.from_bytes::<u8>(&[1, 2]),
+        Err(Error::TrailingBytes(1))
+    );
+    assert_eq!(
+        options.from_bytes::<Kind>(&[4]),
+        Err(Error::UnknownVariant(4))
+    );
+    assert_eq!(
+        options.from_bytes::<String>(&[2, 0xc3, 0x28]),
+        Err(Error::InvalidUtf8)
+    );
+    assert!(options.from_bytes::<char>(&[2, b'a', b'b']).is_err());
+    assert_eq!(
+        options.dictionary(true).from_bytes::<Vec<String>>(&[1, 1]),
+        Err(Error::UnknownString(1))
+    );
+    assert_eq!(
+        options.packed_flags(true).from_bytes::<Kind>(&[3, 0]),
+        Err(Error::Truncated)
+    );
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ya_redis_derive::{
+    encoding,
+    schema::{Encoding, Field, Fields, OptionsSchema, Schema, Variant},
+    Redis, RedisSchema,
+};
+
+#[derive(Redis, RedisSchema, Deserialize, Serialize)]
+#[redis(dictionary, checksum, compress_above = 4096)]
+struct User {
+    id: i64,
+    name: String,
+    email: Option<String>,
+    #[serde(with = "encoding::delta")]
+    friend_ids: Vec<i64>,
+    tags: HashMap<String, u8>,
+    kind: Kind,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    cache: Vec<u8>,
+}
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+enum Kind {
+    A,
+    B(u16),
+    C(bool, char),
+    D { x: (u8, f32) },
+}
+
+fn boxed(schema: Schema) -> Box<Schema> {
+    Box::new(schema)
+}
+
+#[test]
+fn struct_named() {
+    let schema = User::value_schema();
+    assert_eq!(
+        schema.options,
+        OptionsSchema {
+            dictionary: true,
+            checksum: true,
+            compress_above: Some(4096),
+            ..OptionsSchema::default()
+        }
+    );
+    assert_eq!(
+        schema.schema,
+        Schema::Struct {
+            name: "User".into(),
+            fields: Fields::Named(vec![
+                Field::new("id", Schema::I64),
+                Field::new("name", Schema::String),
+                Field::new(
+                    "email",
+                    Schema::Option {
+                        item: boxed(Schema::String)
+                    }
+                ),
+                Field::new(
+                    "friend_ids",
+                    Schema::Encoded {
+                        encoding: Encoding::Delta,
+                        schema: boxed(Schema::Seq {
+                            item: boxed(Schema::I64)
+                        }),
+                    }
+                ),
+                Field::new(
+                    "tags",
+                    Schema::Map {
+                        key: boxed(Schema::String),
+                        value: boxed(Schema::U8),
+                    }
+                ),
+                Field::new("kind", Kind::schema()),
+            ]),
+        }
+    );
+}
+
+#[test]
+fn enum_variants() {
+    assert_eq!(
+        Kind::schema(),
+        Schema::Enum {
+            name: "Kind".into(),
+            variants: vec![
+                Variant::new("A", Fields::Unit),
+                Variant::new("B", Fields::Newtype(boxed(Schema::U16))),
+                Variant::new("C", Fields::Tuple(vec![Schema::Bool, Schema::Char])),
+                Variant::new(
+                    "D",
+                    Fields::Named(vec![Field::new(
+                        "x",
+                        Schema::Tuple {
+                            items: vec![Schema::U8, Schema::F32]
+                        }
+                    )])
+                ),
+            ],
+        }
+    );
+    assert_eq!(Kind::value_schema().options, OptionsSchema::default());
+}
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+struct Wrapper<T>(T);
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+struct Pair(u8, [i8; 2]);
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+struct Unit;
+
+#[test]
+fn struct_unnamed() {
+    assert_eq!(
+        Wrapper::<Wrapper<bool>>::schema(),
+        Schema::Struct {
+            name: "Wrapper".into(),
+            fields: Fields::Newtype(boxed(Schema::Struct {
+                name: "Wrapper".into(),
+                fields: Fields::Newtype(boxed(Schema::Bool)),
+            })),
+        }
+    );
+    assert_eq!(
+        Pair::schema(),
+        Schema::Struct {
+            name: "Pair".into(),
+            fields: Fields::Tuple(vec![
+                Schema::U8,
+                Schema::Tuple {
+                    items: vec![Schema::I8, Schema::I8]
+                }
+            ]),
+        }
+    );
+    assert_eq!(
+        Unit::schema(),
+        Schema::Struct {
+            name: "Unit".into(),
+            fields: Fields::Unit,
+        }
+    );
+}
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+struct Node {
+    value: i32,
+    children: Vec<Node>,
+}
+
+#[test]
+fn recursive() {
+    assert_eq!(
+        Node::schema(),
+        Schema::Struct {
+            name: "Node".into(),
+            fields: Fields::Named(vec![
+                Field::new("value", Schema::I32),
+                Field::new(
+                    "children",
+                    Schema::Seq {
+                        item: boxed(Schema::Ref {
+                            name: "Node".into()
+                        })
+                    }
+                ),
+            ]),
+        }
+    );
+}
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+#[serde(rename = "account", rename_all = "camelCase")]
+struct Renamed {
+    user_id: i64,
+    #[serde(rename = "mail")]
+    email: String,
+    state: State,
+}
+
+#[derive(RedisSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", rename_all_fields = "UPPERCASE")]
+enum State {
+    OnHold {
+        since_ms: u64,
+    },
+    #[serde(rename = "done", rename_all = "PascalCase")]
+    Closed {
+        closed_by: u8,
+    },
+}
+
+#[test]
+fn serde_names() {
+    assert_eq!(
+        Renamed::schema(),
+        Schema::Struct {
+            name: "account".into(),
+            fields: Fields::Named(vec![
+                Field::new("userId", Schema::I64),
+                Field::new("mail", Schema::String),
+                Field::new("state", State::schema()),
+            ]),
+        }
+    );
+    assert_eq!(
+        State::schema(),
+        Schema::Enum {
+            name: "State".into(),
+            variants: vec![
+                Variant::new(
+                    "on_hold",
+                    Fields::Named(vec![Field::new("SINCE_MS", Schema::U64)])
+                ),
+                Variant::new(
+                    "done",
+                    Fields::Named(vec![Field::new("ClosedBy", Schema::U8)])
+                ),
+            ],
+        }
+    );
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::MockRedis;
+use redis::{Commands, FromRedisValue, ToRedisArgs, Value};
+use ya_redis_derive::Redis;
+
+#[derive(Debug, Clone, Copy, PartialEq, Redis)]
+#[redis(transparent)]
+struct Counter(i64);
+
+#[derive(Debug, Clone, PartialEq, Redis)]
+#[redis(transparent)]
+struct Name {
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Redis)]
+#[redis(transparent)]
+struct Ratio(f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Redis)]
+#[redis(transparent)]
+struct Enabled(bool);
+
+#[test]
+fn text() {
+    assert_eq!(Counter(-12).to_redis_args(), [b"-12".to_vec()]);
+    assert_eq!(
+        Name {
+            value: "ann".into()
+        }
+        .to_redis_args(),
+        [b"ann".to_vec()]
+    );
+    assert_eq!(Ratio(0.5).to_redis_args(), [b"0.5".to_vec()]);
+    assert_eq!(Enabled(true).to_redis_args(), [b"1".to_vec()]);
+
+    assert_eq!(
+        Counter::from_redis_value(&Value::Int(3)).unwrap(),
+        Counter(3)
+    );
+    assert_eq!(
+        Counter::from_redis_value(&Value::Data(b"4".to_vec())).unwrap(),
+        Counter(4)
+    );
+    assert_eq!(
+        Name::from_redis_value(&Value::Status("OK".into())).unwrap(),
+        Name { value: "OK".into() }
+    );
+    assert_eq!(
+        Ratio::from_redis_value(&Value::Data(b"1.25".to_vec())).unwrap(),
+        Ratio(1.25)
+    );
+    assert_eq!(
+        Enabled::from_redis_value(&Value::Int(0)).unwrap(),
+        Enabled(false)
+    );
+    assert!(Counter::from_redis_value(&Value::Data(b"x".to_vec())).is_err());
+}
+
+#[test]
+fn incr() {
+    let mut con = MockRedis::new();
+    let _: () = con.set("views", Counter(41)).unwrap();
+    let n: Counter = con.incr("views", 1).unwrap();
+    assert_eq!(n, Counter(42));
+    let n: Counter = con.get("views").unwrap();
+    assert_eq!(n, Counter(42));
+}
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use ya_binary_format::value::{self, Value};
+use ya_redis_derive::{encoding, Error, Options, Redis, RedisSchema};
+
+#[derive(Debug, Clone, PartialEq, Redis, RedisSchema, Deserialize, Serialize)]
+#[redis(dictionary, packed_flags, checksum, compress)]
+struct User {
+    id: i64,
+    name: String,
+    email: Option<String>,
+    active: bool,
+    #[serde(with = "encoding::delta")]
+    friend_ids: Vec<u32>,
+    scores: BTreeMap<u16, f32>,
+    kinds: Vec<Kind>,
+    initial: char,
+    pair: (i8, Option<bool>),
+    parent: Option<Box<User>>,
+}
+
+#[derive(Debug, Clone, PartialEq, RedisSchema, Deserialize, Serialize)]
+enum Kind {
+    A,
+    B(u16),
+    C(bool, String),
+    D { x: u8 },
+}
+
+fn user() -> User {
+    User {
+        id: -3,
+        name: "名無し".into(),
+        email: None,
+        active: true,
+        friend_ids: vec![1, 5, 4_000_000_000],
+        scores: [(1, 0.5), (10, -2.0)].into_iter().collect(),
+        kinds: vec![
+            Kind::A,
+            Kind::B(7),
+            Kind::C(false, "名無し".into()),
+            Kind::D { x: 1 },
+        ],
+        initial: 'ä',
+        pair: (-1, Some(false)),
+        parent: Some(Box::new(User {
+            id: 1,
+            name: "root".into(),
+            email: Some("root@example.com".into()),
+            active: false,
+            friend_ids: vec![],
+            scores: BTreeMap::new(),
+            kinds: vec![],
+            initial: 'r',
+            pair: (0, None),
+            parent: None,
+        })),
+    }
+}
+
+#[test]
+fn decode_and_encode() {
+    let schema = User::value_schema();
+    let options = schema.options.to_options().unwrap();
+    let buf = options.to_bytes(&user());
+    let v = schema.decode(&buf).unwrap();
+    let json = serde_json::to_value(&v).unwrap();
+    assert_eq!(json["id"], -3);
+    assert_eq!(json["name"], "名無し");
+    assert_eq!(json["email"], serde_json::Value::Null);
+    assert_eq!(
+        json["friend_ids"],
+        serde_json::json!([1, 5, 4_000_000_000u32])
+    );
+    assert_eq!(json["scores"]["10"], -2.0);
+    assert_eq!(
+        json["kinds"],
+        serde_json::json!(["A", {"B": 7}, {"C": [false, "名無し"]}, {"D": {"x": 1}}])
+    );
+    assert_eq!(json["initial"], "ä");
+    assert_eq!(json["parent"]["email"], "root@example.com");
+    assert_eq!(schema.encode(&v).unwrap(), buf);
+}
+
+#[test]
+fn encode_json() {
+    let schema = User::value_schema();
+    let json = r#"{
+        "id": 7,
+        "name": "abc",
+        "active": true,
+        "friend_ids": [3, 2, 1],
+        "scores": {"3": 1.5},
+        "kinds": [{"D": {"x": 9}}, "A"],
+        "initial": "x",
+        "pair": [5, null],
+        "parent": null
+    }"#;
+    let v: Value = serde_json::from_str(json).unwrap();
+    let buf = schema.encode(&v).unwrap();
+    let options = schema.options.to_options().unwrap();
+    assert_eq!(
+        options.from_bytes::<User>(&buf).unwrap(),
+        User {
+            id: 7,
+            name: "abc".into(),
+            email: None,
+            active: true,
+            friend_ids: vec![3, 2, 1],
+            scores: [(3, 1.5)].into_iter().collect(),
+            kinds: vec![Kind::D { x: 9 }, Kind::A],
+            initial: 'x',
+            pair: (5, None),
+            parent: None,
+        }
+    );
+}
+
+#[test]
+fn mismatch() {
+    let schema = User::value_schema();
+    let mut v = schema
+        .decode(&schema.options.to_options().unwrap().to_bytes(&user()))
+        .unwrap();
+    if let Value::Object(fields) = &mut v {
+        fields[6].1 = serde_json::from_str(r#"[{"B": -1}]"#).unwrap();
+    }
+    assert_eq!(
+        schema.encode(&v),
+        Err(Error::Mismatch {
+            path: ".kinds[0].B".into(),
+            expected: "u16"
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, RedisSchema, Deserialize, Serialize)]
+struct Maps {
+    by_id: HashMap<u64, Vec<u8>>,
+    by_pair: Vec<(String, ())>,
+}
+
+#[test]
+fn maps() {
+    let maps = Maps {
+        by_id: [(42, vec![1, 2])].into_iter().collect(),
+        by_pair: vec![("a".into(), ())],
+    };
+    let schema = Maps::value_schema();
+    let buf = Options::new().to_bytes(&maps);
+    let v = schema.decode(&buf).unwrap();
+    assert_eq!(
+        v,
+        Value::Object(vec![
+            (
+                "by_id".into(),
+                Value::Map(vec![(
+                    Value::UInt(42),
+                    Value::Array(vec![Value::UInt(1), Value::UInt(2)])
+                )])
+            ),
+            (
+                "by_pair".into(),
+                Value::Array(vec![Value::Array(vec![
+                    Value::String("a".into()),
+                    Value::Null
+                ])])
+            ),
+        ])
+    );
+    let json = serde_json::to_string(&v).unwrap();
+    assert_eq!(json, r#"{"by_id":{"42":[1,2]},"by_pair":[["a",null]]}"#);
+    let v: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(schema.encode(&v).unwrap(), buf);
+}
+
+#[test]
+fn garbage() {
+    let schema = User::value_schema();
+    let options = Options::new();
+    for n in 0..64u8 {
+        let b: Vec<u8> = (0..n).map(|i| i.wrapping_mul(37) ^ n).collect();
+        assert!(value::from_bytes(&options, &schema.schema, &b).is_err());
+    }
+    let buf = [255, 255, 255, 255, 255, 255, 255, 255, 255];
+    assert_eq!(
+        value::from_bytes(&options, &<Vec<()>>::schema(), &buf),
+        Err(Error::TooLarge)
+    );
+}
+
+#[test]
+fn encrypted_values_need_keys() {
+    #[derive(RedisSchema, Deserialize, Serialize)]
+    #[redis(encrypt, key_provider = KEYS)]
+    struct Secret(String);
+
+    struct Keys;
+
+    impl ya_binary_format::KeyProvider for Keys {
+        fn current_key(&self) -> (u32, [u8; 32]) {
+            (1, [1; 32])
+        }
+
+        fn key(&self, id: u32) -> Option<[u8; 32]> {
+            (id == 1).then_some([1; 32])
+        }
+    }
+
+    static KEYS: Keys = Keys;
+
+    let schema = Secret::value_schema();
+    assert!(schema.options.encrypt_value);
+    assert!(matches!(schema.decode(&[]), Err(Error::Unsupported(_))));
+
+    let options = Options::new().encrypt_value(&KEYS);
+    let buf = options.to_bytes(&Secret("abc".into()));
+    let v = value::from_bytes(&options, &schema.schema, &buf).unwrap();
+    assert_eq!(v, Value::String("abc".into()));
+}
+
+#[test]
+fn explain() {
+    let schema = User::value_schema();
+    let buf = schema.options.to_options().unwrap().to_bytes(&user());
+    let explained = schema.explain(&buf).unwrap();
+    assert_eq!(explained.value, schema.decode(&buf).unwrap());
+    let mut end = 0;
+    for span in &explained.spans {
+        assert_eq!(span.start, end, "{:?}", span);
+        end = span.end;
+    }
+    assert_eq!(end, explained.payload.len());
+    let find = |path: &str| {
+        explained
+            .spans
+            .iter()
+            .filter(|s| s.path == path)
+            .map(|s| (s.end - s.start, s.what.as_str()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(find(""), [(2, "flags")]);
+    assert_eq!(find(".active"), [(0, "bool true")]);
+    assert_eq!(find(".email"), [(0, "none")]);
+    assert_eq!(find(".id"), [(8, "i64 -3")]);
+    assert_eq!(find(".kinds"), [(1, "length 4")]);
+    assert_eq!(find(".kinds[1]"), [(1, "variant 1 (B)")]);
+    assert_eq!(find(".kinds[2].C[1]"), [(1, "string \"名無し\"")]);
+    assert_eq!(find(".parent.name"), [(6, "string \"root\"")]);
+}
+
+#[derive(Debug, Clone, PartialEq, RedisSchema, Deserialize, Serialize)]
+struct Empty;
+
+#[derive(Debug, Clone, PartialEq, RedisSchema, Deserialize, Serialize)]
+struct Nested(Option<Option<u8>>);
+
+#[test]
+fn ambiguous_nulls() {
+    let options = Options::new();
+    let unsupported = |e: Error| matches!(e, Error::Unsupported(_));
+    // `Some(None)` and `None` would both be null
+    let buf = options.to_bytes(&Nested(Some(None)));
+    let schema = Nested::schema();
+    assert!(value::from_bytes(&options, &schema, &buf).is_err_and(unsupported));
+    assert!(value::to_bytes(&options, &schema, &Value::Null).is_err_and(unsupported));
+    for schema in [<Option<()>>::schema(), <Option<Empty>>::schema()] {
+        let buf = options.to_bytes(&Some(()));
+        assert!(value::from_bytes(&options, &schema, &buf).is_err_and(unsupported));
+    }
+    // nulls are fine where they are not inside options
+    let schema = <(Empty, Option<u8>)>::schema();
+    let buf = options.to_bytes(&(Empty, None::<u8>));
+    let v = value::from_bytes(&options, &schema, &buf).unwrap();
+    assert_eq!(v, Value::Array(vec![Value::Null, Value::Null]));
+    assert_eq!(value::to_bytes(&options, &schema, &v).unwrap(), buf);
+}
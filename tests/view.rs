@@ -0,0 +1,169 @@
+use redis::{FromRedisValue, ToRedisArgs, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ya_binary_format::KeyProvider;
+use ya_redis_derive::{
+    encoding,
+    schema::{Field, Fields, Schema},
+    view::View,
+    Error, Options, Redis, RedisSchema,
+};
+
+struct Keys;
+
+impl KeyProvider for Keys {
+    fn current_key(&self) -> (u32, [u8; 32]) {
+        (1, [1; 32])
+    }
+
+    fn key(&self, id: u32) -> Option<[u8; 32]> {
+        (id == 1).then_some([1; 32])
+    }
+}
+
+static KEYS: Keys = Keys;
+
+#[derive(Debug, Clone, PartialEq, Redis, RedisSchema, Deserialize, Serialize)]
+#[redis(view, dictionary, packed_flags, checksum, compress, key_provider = KEYS)]
+struct User {
+    id: i64,
+    name: String,
+    active: bool,
+    #[redis(encrypt)]
+    email: Option<String>,
+    #[serde(with = "encoding::delta")]
+    friend_ids: Vec<u32>,
+    kinds: Vec<Kind>,
+    nick: Option<String>,
+    tags: BTreeMap<String, Option<bool>>,
+    parent: Option<Box<User>>,
+    #[serde(skip)]
+    cache: Vec<u8>,
+    score: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, RedisSchema, Deserialize, Serialize)]
+enum Kind {
+    A,
+    B(u16),
+    C { name: String, flag: bool },
+}
+
+fn user() -> User {
+    User {
+        id: -3,
+        name: "名無し".into(),
+        active: true,
+        email: Some("a@example.com".into()),
+        friend_ids: (0..2000).collect(),
+        kinds: vec![
+            Kind::A,
+            Kind::B(7),
+            Kind::C {
+                name: "名無し".into(),
+                flag: true,
+            },
+        ],
+        nick: Some("名無し".into()),
+        tags: [("x".into(), None), ("名無し".into(), Some(false))]
+            .into_iter()
+            .collect(),
+        parent: Some(Box::new(User {
+            id: 1,
+            name: "root".into(),
+            active: false,
+            email: None,
+            friend_ids: vec![],
+            kinds: vec![],
+            nick: Some("root".into()),
+            tags: BTreeMap::new(),
+            parent: None,
+            cache: vec![],
+            score: 1,
+        })),
+        cache: vec![],
+        score: 42,
+    }
+}
+
+fn encoded() -> Vec<u8> {
+    let mut args = user().to_redis_args();
+    args.pop().unwrap()
+}
+
+#[test]
+fn fields() {
+    let buf = encoded();
+    let view = UserView::new(&buf).unwrap();
+    let user = user();
+    assert_eq!(view.score().unwrap(), 42);
+    assert_eq!(view.nick().unwrap(), user.nick);
+    assert_eq!(view.email().unwrap(), user.email);
+    assert_eq!(view.tags().unwrap(), user.tags);
+    assert_eq!(view.kinds().unwrap(), user.kinds);
+    assert_eq!(view.friend_ids().unwrap(), user.friend_ids);
+    assert_eq!(view.active().unwrap(), user.active);
+    assert_eq!(view.name().unwrap(), user.name);
+    assert_eq!(view.id().unwrap(), user.id);
+    assert_eq!(view.parent().unwrap(), user.parent);
+}
+
+#[test]
+fn from_redis_value() {
+    let view = UserView::from_redis_value(&Value::Data(encoded())).unwrap();
+    assert_eq!(view.score().unwrap(), 42);
+    assert!(UserView::from_redis_value(&Value::Nil).is_err());
+}
+
+#[test]
+fn corrupted() {
+    let mut buf = encoded();
+    *buf.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        UserView::new(&buf),
+        Err(Error::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn plain_view() {
+    let schema = Schema::Struct {
+        name: "Pair".into(),
+        fields: Fields::Named(vec![
+            Field::new("a", <Vec<String>>::schema()),
+            Field::new("b", Schema::U8),
+        ]),
+    };
+    let buf = Options::new().to_bytes(&(vec!["x"; 100], 3u8));
+    let view = View::from_vec(&Options::new(), buf).unwrap();
+    assert_eq!(view.field::<u8>(&schema, "b").unwrap(), 3);
+    assert!(matches!(
+        view.field::<u8>(&schema, "c"),
+        Err(Error::Unsupported(_))
+    ));
+    assert!(matches!(
+        view.field::<u8>(&Schema::U8, "b"),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[derive(Debug, Clone, PartialEq, Redis, RedisSchema, Deserialize, Serialize)]
+#[redis(view)]
+#[serde(rename_all = "camelCase")]
+struct Renamed {
+    first_name: String,
+    #[serde(rename = "years")]
+    age: u8,
+}
+
+#[test]
+fn renamed_fields() {
+    let r = Renamed {
+        first_name: "x".into(),
+        age: 3,
+    };
+    let buf = r.to_redis_args().pop().unwrap();
+    let view = RenamedView::new(&buf).unwrap();
+    assert_eq!(view.first_name().unwrap(), "x");
+    assert_eq!(view.age().unwrap(), 3);
+}
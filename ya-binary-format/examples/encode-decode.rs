@@ -35,6 +35,6 @@ fn main() {
     println!("{:?}", buf);
     println!("{}", buf.len());
 
-    let v: MyStruct = from_bytes(&buf);
+    let v: MyStruct = from_bytes(&buf).unwrap();
     assert_eq!(a, v);
 }
@@ -0,0 +1,89 @@
+//! Decode arbitrary bytes as a set of types covering every shape the serializer emits.
+//!
+//! The first byte selects the options, the rest is the value.
+//! Run with `cargo +nightly fuzz run decode` in `ya-binary-format`.
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use libfuzzer_sys::fuzz_target;
+use serde::Deserialize;
+use ya_binary_format::{encoding, KeyProvider, Options};
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+enum Kind {
+    Unit,
+    Newtype(u16),
+    Tuple(bool, String),
+    Struct { a: bool, b: Option<u32> },
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Record {
+    id: u64,
+    small: i8,
+    big: i128,
+    ratio: f64,
+    initial: char,
+    name: String,
+    tag: Option<String>,
+    active: bool,
+    kinds: Vec<Kind>,
+    attrs: BTreeMap<String, Vec<u8>>,
+    nested: Vec<Vec<Option<i16>>>,
+    pair: (u8, Option<bool>),
+    #[serde(with = "encoding::delta")]
+    ids: Vec<i64>,
+    #[serde(with = "encoding::delta_of_delta")]
+    timestamps: Vec<u32>,
+    #[serde(with = "encoding::rle")]
+    states: Vec<u8>,
+    secret: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Node {
+    value: i32,
+    next: Option<Box<Node>>,
+}
+
+struct Key;
+
+impl KeyProvider for Key {
+    fn current_key(&self) -> (u32, [u8; 32]) {
+        (0, [0; 32])
+    }
+
+    fn key(&self, id: u32) -> Option<[u8; 32]> {
+        (id == 0).then_some([0; 32])
+    }
+}
+
+fn options(bits: u8) -> Options {
+    let options = Options::new()
+        .dictionary(bits & 1 != 0)
+        .packed_flags(bits & 2 != 0)
+        .checksum(bits & 4 != 0)
+        .compress(bits & 8 != 0);
+    match bits >> 4 & 3 {
+        1 => options.encrypt_value(&Key),
+        2 => options.encrypt_fields(&Key, "Record", &["secret"]),
+        _ => options,
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&bits, b)) = data.split_first() else {
+        return;
+    };
+    let options = options(bits);
+    let _ = options.from_bytes::<Record>(b);
+    let _ = options.from_bytes::<Kind>(b);
+    let _ = options.from_bytes::<Vec<Kind>>(b);
+    let _ = options.from_bytes::<Node>(b);
+    let _ = options.from_bytes::<BTreeMap<String, Option<Vec<u8>>>>(b);
+    let _ = options.from_bytes::<Vec<Vec<Option<(bool, char)>>>>(b);
+});
@@ -1,5 +1,6 @@
-use bytes::Buf;
+use crate::error::Error;
 
+/// Bounds checked reader over the input.
 pub(crate) struct Bytes<'a> {
     data: &'a [u8],
 }
@@ -8,18 +9,23 @@ impl<'a> Bytes<'a> {
     pub(crate) fn new(data: &'a [u8]) -> Bytes<'a> {
         Bytes { data }
     }
-}
 
-impl<'a> Buf for Bytes<'a> {
-    fn remaining(&self) -> usize {
-        self.data.remaining()
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.len()
     }
 
-    fn chunk(&self) -> &[u8] {
-        self.data
+    pub(crate) fn take_slice(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.data.len() {
+            return Err(Error::Truncated);
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
     }
 
-    fn advance(&mut self, cnt: usize) {
-        self.data = &self.data[cnt..];
+    pub(crate) fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut a = [0; N];
+        a.copy_from_slice(self.take_slice(N)?);
+        Ok(a)
     }
 }
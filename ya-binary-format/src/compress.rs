@@ -0,0 +1,44 @@
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::error::Error;
+
+const RAW: u8 = 0;
+const LZ4: u8 = 1;
+
+const MAX_RATIO: usize = 255;
+
+/// Prepend the header and compress the value if it is larger than `threshold`.
+pub(crate) fn compress(raw: &[u8], threshold: usize) -> Vec<u8> {
+    if raw.len() > threshold {
+        let compressed = lz4_flex::compress_prepend_size(raw);
+        if compressed.len() < raw.len() {
+            let mut buf = Vec::with_capacity(compressed.len() + 1);
+            buf.push(LZ4);
+            buf.extend_from_slice(&compressed);
+            return buf;
+        }
+    }
+    let mut buf = Vec::with_capacity(raw.len() + 1);
+    buf.push(RAW);
+    buf.extend_from_slice(raw);
+    buf
+}
+
+pub(crate) fn decompress(b: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    match b.split_first() {
+        Some((&RAW, raw)) => Ok(Cow::Borrowed(raw)),
+        Some((&LZ4, compressed)) => {
+            let (size, block) = compressed.split_at_checked(4).ok_or(Error::Truncated)?;
+            let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+            // LZ4 cannot expand a block more than 255 times, so a larger size is a lie
+            if size > block.len().saturating_mul(MAX_RATIO) {
+                return Err(Error::InvalidCompressedData);
+            }
+            lz4_flex::decompress(block, size)
+                .map(Cow::Owned)
+                .map_err(|_| Error::InvalidCompressedData)
+        }
+        Some((&header, _)) => Err(Error::InvalidHeader(header)),
+        None => Err(Error::Truncated),
+    }
+}
@@ -1,131 +1,288 @@
-use bytes::Buf;
-use serde::{
-    de::{self, Deserialize, DeserializeSeed, Visitor},
-    serde_if_integer128,
-};
+use alloc::{string::ToString, vec::Vec};
+
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor};
+
+#[cfg(feature = "encrypt")]
+use crate::encrypt::{self, Encryption};
+use crate::{bytes::Bytes, error::Error, options::Options};
+
+/// Maximum nesting of options, sequences, maps, structs and enums.
+pub const MAX_DEPTH: usize = 128;
+
+/// Bytes which dictionary references may expand to, per byte of input.
+const MAX_DICTIONARY_EXPANSION: usize = 256;
+
+/// Deserializer over the bytes of lifetime `'b`.
+///
+/// It never borrows from the bytes, so it implements `serde::Deserializer<'de>` for any `'de`.
+/// This lets nested values be decoded from buffers owned by the deserializer, such as decrypted fields.
+///
+/// Decoding never panics on arbitrary input: malformed values are reported as [`Error`]s.
+/// Nesting is limited to [`MAX_DEPTH`], and memory is allocated in proportion to the input,
+/// so a short value cannot claim a huge length or expand without bound through the dictionary.
+/// The exception is [`crate::encoding::rle`], whose runs expand up to
+/// [`crate::encoding::rle::MAX_LEN`] elements per field.
+pub struct Deserializer<'b> {
+    data: Bytes<'b>,
+    options: Options,
+    depth: usize,
+    strings: Vec<&'b str>,
+    /// Bytes left for strings read through dictionary references
+    expansion: usize,
+    /// Bitmap and read position of the innermost struct in packed flags mode
+    flags: Vec<(&'b [u8], usize)>,
+}
 
-use crate::{bytes::Bytes, never::Never};
+impl<'b> Deserializer<'b> {
+    pub(crate) fn new(data: &'b [u8], options: Options) -> Deserializer<'b> {
+        Deserializer {
+            data: Bytes::new(data),
+            options,
+            depth: 0,
+            strings: Vec::new(),
+            expansion: data.len().saturating_mul(MAX_DICTIONARY_EXPANSION),
+            flags: Vec::new(),
+        }
+    }
 
-pub struct Deserializer<'de> {
-    data: Bytes<'de>,
-    current_variant_name: Option<&'static str>,
-}
+    /// Fail unless the whole input has been consumed.
+    pub(crate) fn end(&self) -> Result<(), Error> {
+        match self.data.remaining() {
+            0 => Ok(()),
+            n => Err(Error::TrailingBytes(n)),
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.data.remaining()
+    }
+
+    pub(crate) fn take_slice(&mut self, n: usize) -> Result<&'b [u8], Error> {
+        self.data.take_slice(n)
+    }
+
+    pub(crate) fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        self.data.take_array()
+    }
+
+    pub(crate) fn get_u8(&mut self) -> Result<u8, Error> {
+        self.data.take_array().map(|[b]| b)
+    }
+
+    pub(crate) fn get_flag(&mut self) -> Result<bool, Error> {
+        match self.flags.last_mut() {
+            Some((bitmap, pos)) => {
+                let byte = bitmap.get(*pos / 8).ok_or(Error::Truncated)?;
+                let flag = byte & (1 << (*pos % 8)) != 0;
+                *pos += 1;
+                Ok(flag)
+            }
+            None => Ok(self.get_u8()? != b'0'),
+        }
+    }
+
+    pub(crate) fn get_len(&mut self) -> Result<usize, Error> {
+        let len = match self.get_u8()? {
+            254 => u32::from_le_bytes(self.data.take_array()?) as u64,
+            255 => u64::from_le_bytes(self.data.take_array()?),
+            len => len as u64,
+        };
+        usize::try_from(len).map_err(|_| Error::InvalidLength(len))
+    }
+
+    pub(crate) fn get_str(&mut self) -> Result<&'b str, Error> {
+        if self.options.dictionary {
+            match self.get_len()? {
+                0 => {}
+                index => {
+                    let s = *self
+                        .strings
+                        .get(index - 1)
+                        .ok_or(Error::UnknownString(index))?;
+                    self.expansion = self.expansion.checked_sub(s.len()).ok_or(Error::TooLarge)?;
+                    return Ok(s);
+                }
+            }
+        }
+        let n = self.get_len()?;
+        let s = core::str::from_utf8(self.data.take_slice(n)?).map_err(|_| Error::InvalidUtf8)?;
+        if self.options.dictionary {
+            self.strings.push(s);
+        }
+        Ok(s)
+    }
 
-impl<'de> Deserializer<'de> {
-    fn get_len(&mut self) -> usize {
-        match self.data.get_u8() {
-            254 => self.data.get_u32_le() as usize,
-            255 => self.data.get_u64_le() as usize,
-            len => len as usize,
+    /// Read the flags bitmap of a struct in packed flags mode.
+    pub(crate) fn begin_struct(&mut self) -> Result<(), Error> {
+        if self.options.packed_flags {
+            let n = self.get_len()?;
+            let bitmap = self.data.take_slice(n)?;
+            self.flags.push((bitmap, 0));
         }
+        Ok(())
+    }
+
+    pub(crate) fn end_struct(&mut self) {
+        if self.options.packed_flags {
+            self.flags.pop();
+        }
+    }
+
+    /// Encryption of the fields of the struct named `name`, if any.
+    #[cfg(feature = "encrypt")]
+    pub(crate) fn field_encryption(&self, name: &str) -> Option<Encryption> {
+        self.options.encryption.filter(|e| e.has_fields_of(name))
+    }
+
+    /// Decrypt the `field` of the struct named `name` and decode it with `f`.
+    #[cfg(feature = "encrypt")]
+    pub(crate) fn decode_encrypted<R>(
+        &mut self,
+        encryption: &Encryption,
+        name: &str,
+        field: &str,
+        f: impl FnOnce(&mut Deserializer<'_>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let n = self.get_len()?;
+        let sealed = self.data.take_slice(n)?;
+        let aad = Encryption::field_aad(name, field);
+        let plaintext = encrypt::open(encryption.provider, &aad, sealed)?;
+        let mut de = Deserializer::new(&plaintext, self.options);
+        de.depth = self.depth;
+        let v = f(&mut de)?;
+        de.end()?;
+        Ok(v)
+    }
+
+    /// Run `f` one level deeper, failing beyond [`MAX_DEPTH`].
+    pub(crate) fn nested<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(Error::TooDeep);
+        }
+        self.depth += 1;
+        let r = f(self);
+        self.depth -= 1;
+        r
     }
 }
 
-struct FixedAccess<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct FixedAccess<'a, 'b: 'a> {
+    de: &'a mut Deserializer<'b>,
     len: usize,
 }
 
-impl<'a, 'de> FixedAccess<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>, len: usize) -> Self {
+impl<'a, 'b> FixedAccess<'a, 'b> {
+    fn new(de: &'a mut Deserializer<'b>, len: usize) -> Self {
         FixedAccess { de, len }
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
-    type Error = Never;
+/// Access to the fields of a struct some of which are encrypted.
+#[cfg(feature = "encrypt")]
+struct EncryptedFieldsAccess<'a, 'b: 'a> {
+    de: &'a mut Deserializer<'b>,
+    encryption: Encryption,
+    name: &'static str,
+    fields: &'static [&'static str],
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'_> {
+    type Error = Error;
 
     fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
-        unimplemented!()
+        Err(Error::NotSelfDescribing)
     }
 
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_bool(self.data.get_u8() == b'1')
+        visitor.visit_bool(self.get_flag()?)
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_i8(self.data.get_i8())
+        visitor.visit_i8(self.get_u8()? as i8)
     }
 
     fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_i16(self.data.get_i16_le())
+        visitor.visit_i16(i16::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_i32(self.data.get_i32_le())
+        visitor.visit_i32(i32::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_i64(self.data.get_i64_le())
+        visitor.visit_i64(i64::from_le_bytes(self.data.take_array()?))
     }
 
-    serde_if_integer128! {
-        fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-            visitor.visit_i128(self.data.get_i128_le())
-        }
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i128(i128::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_u8(self.data.get_u8())
+        visitor.visit_u8(self.get_u8()?)
     }
 
     fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_u16(self.data.get_u16_le())
+        visitor.visit_u16(u16::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_u32(self.data.get_u32_le())
+        visitor.visit_u32(u32::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_u64(self.data.get_u64_le())
+        visitor.visit_u64(u64::from_le_bytes(self.data.take_array()?))
     }
 
-    serde_if_integer128! {
-        fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-            visitor.visit_u128(self.data.get_u128_le())
-        }
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_u128(u128::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_f32(self.data.get_f32_le())
+        visitor.visit_f32(f32::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_f64(self.data.get_f64_le())
+        visitor.visit_f64(f64::from_le_bytes(self.data.take_array()?))
     }
 
     fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        self.deserialize_str(visitor)
+        let s = self.get_str()?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Str(s),
+                &"a character",
+            )),
+        }
     }
 
-    fn deserialize_str<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
-        unimplemented!()
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.get_str()?)
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        let n = self.get_len();
-        let s = String::from_utf8(self.data.chunk()[..n].to_vec()).expect("Invalid UTF8");
-        self.data.advance(n);
-        visitor.visit_string(s)
+        visitor.visit_string(self.get_str()?.to_string())
     }
 
-    fn deserialize_bytes<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
-        unimplemented!()
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let n = self.get_len()?;
+        visitor.visit_bytes(self.data.take_slice(n)?)
     }
 
     fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        let n = self.get_len();
-        let v = visitor.visit_byte_buf(self.data.chunk()[..n].to_vec());
-        self.data.advance(n);
-        v
+        let n = self.get_len()?;
+        visitor.visit_byte_buf(self.data.take_slice(n)?.to_vec())
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        if self.data.get_u8() == b'0' {
-            visitor.visit_none()
+        if self.get_flag()? {
+            self.nested(|de| visitor.visit_some(de))
         } else {
-            visitor.visit_some(self)
+            visitor.visit_none()
         }
     }
 
@@ -146,12 +303,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         _name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        visitor.visit_newtype_struct(self)
+        self.nested(|de| visitor.visit_newtype_struct(de))
     }
 
     fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        let len = self.get_len();
-        visitor.visit_seq(FixedAccess::new(self, len))
+        let len = self.get_len()?;
+        self.nested(|de| visitor.visit_seq(FixedAccess::new(de, len)))
     }
 
     fn deserialize_tuple<V: Visitor<'de>>(
@@ -159,7 +316,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         len: usize,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        visitor.visit_seq(FixedAccess::new(self, len))
+        self.nested(|de| visitor.visit_seq(FixedAccess::new(de, len)))
     }
 
     fn deserialize_tuple_struct<V: Visitor<'de>>(
@@ -168,12 +325,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         len: usize,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        visitor.visit_seq(FixedAccess::new(self, len))
+        self.nested(|de| visitor.visit_seq(FixedAccess::new(de, len)))
     }
 
     fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        let len = self.get_len();
-        visitor.visit_map(FixedAccess::new(self, len))
+        let len = self.get_len()?;
+        self.nested(|de| visitor.visit_map(FixedAccess::new(de, len)))
     }
 
     fn deserialize_struct<V: Visitor<'de>>(
@@ -182,7 +339,23 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        visitor.visit_seq(FixedAccess::new(self, fields.len()))
+        self.nested(|de| {
+            de.begin_struct()?;
+            #[cfg(feature = "encrypt")]
+            let v = match de.field_encryption(_name) {
+                Some(encryption) => visitor.visit_seq(EncryptedFieldsAccess {
+                    de: &mut *de,
+                    encryption,
+                    name: _name,
+                    fields,
+                }),
+                None => visitor.visit_seq(FixedAccess::new(de, fields.len())),
+            };
+            #[cfg(not(feature = "encrypt"))]
+            let v = visitor.visit_seq(FixedAccess::new(de, fields.len()));
+            de.end_struct();
+            v
+        })
     }
 
     fn deserialize_enum<V: Visitor<'de>>(
@@ -191,25 +364,25 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        let n = self.get_len();
-        self.current_variant_name = Some(variants[n]);
-        visitor.visit_enum(self)
+        let n = self.get_len()?;
+        let variant = *variants.get(n).ok_or(Error::UnknownVariant(n))?;
+        self.nested(|de| visitor.visit_enum(Enum { de, variant }))
     }
 
     fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
-        visitor.visit_str(self.current_variant_name.take().unwrap())
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_ignored_any<V: Visitor<'de>>(
         self,
         _visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        unimplemented!()
+        Err(Error::NotSelfDescribing)
     }
 }
 
-impl<'de, 'a> de::SeqAccess<'de> for FixedAccess<'a, 'de> {
-    type Error = Never;
+impl<'de> de::SeqAccess<'de> for FixedAccess<'_, '_> {
+    type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
     where
@@ -228,8 +401,36 @@ impl<'de, 'a> de::SeqAccess<'de> for FixedAccess<'a, 'de> {
     }
 }
 
-impl<'de, 'a> de::MapAccess<'de> for FixedAccess<'a, 'de> {
-    type Error = Never;
+#[cfg(feature = "encrypt")]
+impl<'de> de::SeqAccess<'de> for EncryptedFieldsAccess<'_, '_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let (&field, rest) = match self.fields.split_first() {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        self.fields = rest;
+        if !self.encryption.is_encrypted_field(self.name, field) {
+            return seed.deserialize(&mut *self.de).map(Some);
+        }
+        self.de
+            .decode_encrypted(&self.encryption, self.name, field, |de| {
+                seed.deserialize(de)
+            })
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+impl<'de> de::MapAccess<'de> for FixedAccess<'_, '_> {
+    type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
     where
@@ -274,20 +475,27 @@ impl<'de, 'a> de::MapAccess<'de> for FixedAccess<'a, 'de> {
     }
 }
 
-impl<'de, 'a> de::EnumAccess<'de> for &'a mut Deserializer<'de> {
-    type Error = Never;
-    type Variant = Self;
+/// Enum whose variant has been read.
+struct Enum<'a, 'b: 'a> {
+    de: &'a mut Deserializer<'b>,
+    variant: &'static str,
+}
+
+impl<'de, 'a, 'b> de::EnumAccess<'de> for Enum<'a, 'b> {
+    type Error = Error;
+    type Variant = &'a mut Deserializer<'b>;
 
     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
     where
         V: DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self).and_then(|v| Ok((v, self)))
+        seed.deserialize(self.variant.into_deserializer())
+            .map(|v| (v, self.de))
     }
 }
 
-impl<'de, 'a> de::VariantAccess<'de> for &'a mut Deserializer<'de> {
-    type Error = Never;
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'_> {
+    type Error = Error;
 
     fn unit_variant(self) -> Result<(), Self::Error> {
         Ok(())
@@ -319,13 +527,9 @@ impl<'de, 'a> de::VariantAccess<'de> for &'a mut Deserializer<'de> {
     }
 }
 
-pub fn from_bytes<'a, T>(b: &'a [u8]) -> T
+pub fn from_bytes<'a, T>(b: &'a [u8]) -> Result<T, Error>
 where
     T: Deserialize<'a>,
 {
-    let mut de = Deserializer {
-        data: Bytes::new(b),
-        current_variant_name: None,
-    };
-    Deserialize::deserialize(&mut de).unwrap()
+    Options::new().deserialize(b)
 }
@@ -0,0 +1,207 @@
+/*!
+Field-level encodings for integer sequences.
+
+Each module is meant to be used with `#[serde(with = "...")]` on a `Vec` of integers.
+The encoded sequence is stored as a single byte string of zigzag varints,
+so it also works with other serde formats.
+
+```rust
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct User {
+    #[serde(with = "ya_binary_format::encoding::delta")]
+    friend_ids: Vec<i64>,
+}
+```
+ */
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::{
+    de::{self, SeqAccess, Unexpected, Visitor},
+    ser, Deserializer, Serializer,
+};
+
+use crate::varint;
+
+/// Integer types which can be encoded by the modules in [`crate::encoding`].
+pub trait Integer: Copy {
+    /// Sign-extended bit pattern of the value.
+    fn to_bits(self) -> u64;
+    /// Inverse of [`Integer::to_bits`], or the value decoded if it does not fit in `Self`.
+    fn from_bits(v: u64) -> Result<Self, Unexpected<'static>>;
+}
+
+macro_rules! impl_integer {
+    ($via:ident, $unexpected:ident => $($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                #[inline]
+                fn to_bits(self) -> u64 {
+                    self as $via as u64
+                }
+
+                #[inline]
+                fn from_bits(v: u64) -> Result<Self, Unexpected<'static>> {
+                    <$t>::try_from(v as $via).map_err(|_| Unexpected::$unexpected(v as $via))
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i64, Signed => i8, i16, i32, i64, isize);
+impl_integer!(u64, Unsigned => u8, u16, u32, u64, usize);
+
+fn truncated<E: de::Error>() -> E {
+    E::custom("truncated varint")
+}
+
+/// [`Integer::from_bits`], failing for values which do not fit in `T`, like those of a sequence
+/// of a wider type.
+fn integer<T: Integer, E: de::Error>(v: u64) -> Result<T, E> {
+    T::from_bits(v)
+        .map_err(|unexpected| E::invalid_value(unexpected, &"an integer of the element type"))
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("encoded integer sequence")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(b) = seq.next_element()? {
+            v.push(b);
+        }
+        Ok(v)
+    }
+}
+
+fn decode<'de, D, T, F>(deserializer: D, mut f: F) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnMut(&mut &[u8], &mut Vec<T>) -> Result<(), D::Error>,
+{
+    let buf = deserializer.deserialize_byte_buf(BytesVisitor)?;
+    let mut data = buf.as_slice();
+    let mut v = Vec::new();
+    while !data.is_empty() {
+        f(&mut data, &mut v)?;
+    }
+    Ok(v)
+}
+
+/// Delta + zigzag varint encoding. Best for sorted ids.
+pub mod delta {
+    use super::*;
+
+    pub fn serialize<T: Integer, S: Serializer>(v: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = Vec::with_capacity(v.len());
+        let mut prev = 0u64;
+        for x in v {
+            let x = x.to_bits();
+            varint::write_i64(&mut buf, x.wrapping_sub(prev) as i64);
+            prev = x;
+        }
+        serializer.serialize_bytes(&buf)
+    }
+
+    pub fn deserialize<'de, T: Integer, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        let mut prev = 0u64;
+        decode(deserializer, |data, v| {
+            prev = prev.wrapping_add(varint::read_i64(data).ok_or_else(truncated)? as u64);
+            v.push(integer(prev)?);
+            Ok(())
+        })
+    }
+}
+
+/// Delta-of-delta + zigzag varint encoding. Best for regularly spaced timestamps.
+pub mod delta_of_delta {
+    use super::*;
+
+    pub fn serialize<T: Integer, S: Serializer>(v: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = Vec::with_capacity(v.len());
+        let mut prev = 0u64;
+        let mut prev_delta = 0u64;
+        for x in v {
+            let x = x.to_bits();
+            let delta = x.wrapping_sub(prev);
+            varint::write_i64(&mut buf, delta.wrapping_sub(prev_delta) as i64);
+            prev = x;
+            prev_delta = delta;
+        }
+        serializer.serialize_bytes(&buf)
+    }
+
+    pub fn deserialize<'de, T: Integer, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        let mut prev = 0u64;
+        let mut prev_delta = 0u64;
+        decode(deserializer, |data, v| {
+            prev_delta =
+                prev_delta.wrapping_add(varint::read_i64(data).ok_or_else(truncated)? as u64);
+            prev = prev.wrapping_add(prev_delta);
+            v.push(integer(prev)?);
+            Ok(())
+        })
+    }
+}
+
+/// Run-length encoding as pairs of (zigzag varint value, varint run length).
+pub mod rle {
+    use super::*;
+
+    /// Maximum number of decoded elements, which bounds the memory a short input can claim.
+    pub const MAX_LEN: usize = 1 << 20;
+
+    const TOO_LONG: &str = "run-length encoded sequence is too long";
+
+    pub fn serialize<T: Integer, S: Serializer>(v: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        if v.len() > MAX_LEN {
+            return Err(ser::Error::custom(TOO_LONG));
+        }
+        let mut buf = Vec::new();
+        let mut iter = v.iter().map(|x| x.to_bits()).peekable();
+        while let Some(x) = iter.next() {
+            let mut run = 1;
+            while iter.next_if_eq(&x).is_some() {
+                run += 1;
+            }
+            varint::write_i64(&mut buf, x as i64);
+            varint::write_u64(&mut buf, run);
+        }
+        serializer.serialize_bytes(&buf)
+    }
+
+    pub fn deserialize<'de, T: Integer, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<T>, D::Error> {
+        decode(deserializer, |data, v| {
+            let x: T = integer(varint::read_i64(data).ok_or_else(truncated)? as u64)?;
+            let run = varint::read_u64(data).ok_or_else(truncated)?;
+            match usize::try_from(run) {
+                Ok(run) if run <= MAX_LEN - v.len() => v.extend(core::iter::repeat_n(x, run)),
+                _ => return Err(de::Error::custom(TOO_LONG)),
+            }
+            Ok(())
+        })
+    }
+}
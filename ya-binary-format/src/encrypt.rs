@@ -0,0 +1,100 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+
+use crate::error::Error;
+
+const KEY_ID_SIZE: usize = 4;
+const NONCE_SIZE: usize = 12;
+
+/// Source of the 256-bit ChaCha20-Poly1305 keys.
+///
+/// The id of the key is stored with each encrypted value,
+/// so old keys can still be read after [`KeyProvider::current_key`] is rotated.
+pub trait KeyProvider: Sync {
+    /// Id and key used to encrypt new values.
+    fn current_key(&self) -> (u32, [u8; 32]);
+
+    /// Key with the id, or `None` if it is unknown.
+    fn key(&self, id: u32) -> Option<[u8; 32]>;
+}
+
+/// Encrypt to `[key id (u32 LE)][nonce][ciphertext with tag]`.
+pub(crate) fn seal(provider: &dyn KeyProvider, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let (id, key) = provider.current_key();
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("Too large to encrypt");
+    let mut buf = Vec::with_capacity(KEY_ID_SIZE + NONCE_SIZE + ciphertext.len());
+    buf.extend_from_slice(&id.to_le_bytes());
+    buf.extend_from_slice(&nonce);
+    buf.extend_from_slice(&ciphertext);
+    buf
+}
+
+pub(crate) fn open(
+    provider: &dyn KeyProvider,
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if sealed.len() < KEY_ID_SIZE + NONCE_SIZE {
+        return Err(Error::Truncated);
+    }
+    let (id, rest) = sealed.split_at(KEY_ID_SIZE);
+    let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+    let id = u32::from_le_bytes(id.try_into().unwrap());
+    let key = provider.key(id).ok_or(Error::UnknownKey(id))?;
+    ChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::Decrypt)
+}
+
+/// Encryption settings of [`crate::Options`].
+#[derive(Clone, Copy)]
+pub(crate) struct Encryption {
+    pub(crate) provider: &'static dyn KeyProvider,
+    pub(crate) value: bool,
+    /// Encrypted fields and the name of the struct they belong to
+    pub(crate) fields: Option<(&'static str, &'static [&'static str])>,
+}
+
+impl Encryption {
+    /// Associated data binding an encrypted field to its place.
+    pub(crate) fn field_aad(name: &str, field: &str) -> Vec<u8> {
+        [name.as_bytes(), b".", field.as_bytes()].concat()
+    }
+
+    pub(crate) fn has_fields_of(&self, name: &str) -> bool {
+        matches!(self.fields, Some((n, _)) if n == name)
+    }
+
+    pub(crate) fn is_encrypted_field(&self, name: &str, field: &str) -> bool {
+        matches!(self.fields, Some((n, fields)) if n == name && fields.contains(&field))
+    }
+}
+
+impl fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encryption")
+            .field("value", &self.value)
+            .field("fields", &self.fields)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::string::{String, ToString};
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The checksum trailer does not match the value.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The value ends before everything it declares has been read.
+    Truncated,
+    /// Bytes are left after the value.
+    TrailingBytes(usize),
+    /// A length does not fit in `usize`.
+    InvalidLength(u64),
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
+    /// A variant index beyond the variants of the enum.
+    UnknownVariant(usize),
+    /// A dictionary reference to a string which has not been read yet.
+    UnknownString(usize),
+    /// The value is nested deeper than [`crate::de::MAX_DEPTH`].
+    TooDeep,
+    /// The value would expand to more memory than allowed for its size.
+    TooLarge,
+    /// A `Deserialize` implementation asked for a self-describing format.
+    NotSelfDescribing,
+    /// A [`crate::value::Value`] does not match its schema at `path`.
+    Mismatch {
+        path: String,
+        expected: &'static str,
+    },
+    /// The schema or options cannot be handled.
+    Unsupported(&'static str),
+    /// Unknown compression header.
+    InvalidHeader(u8),
+    /// The compressed payload is broken.
+    InvalidCompressedData,
+    /// The buffer given to `to_slice` is too small.
+    BufferFull,
+    /// No key for the id embedded in an encrypted value.
+    UnknownKey(u32),
+    /// The encrypted value cannot be authenticated.
+    Decrypt,
+    /// Error reported by a `Serialize` or `Deserialize` implementation.
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {:08x}, actual {:08x}",
+                expected, actual
+            ),
+            Error::Truncated => f.write_str("truncated value"),
+            Error::TrailingBytes(n) => write!(f, "{} trailing bytes", n),
+            Error::InvalidLength(len) => write!(f, "invalid length: {}", len),
+            Error::InvalidUtf8 => f.write_str("invalid UTF-8"),
+            Error::UnknownVariant(n) => write!(f, "unknown variant index: {}", n),
+            Error::UnknownString(n) => write!(f, "unknown dictionary string: {}", n),
+            Error::TooDeep => f.write_str("value is nested too deeply"),
+            Error::TooLarge => f.write_str("value expands too much"),
+            Error::NotSelfDescribing => f.write_str("the format is not self-describing"),
+            Error::Mismatch { path, expected } => write!(f, "expected {} at ${}", expected, path),
+            Error::Unsupported(msg) => write!(f, "unsupported: {}", msg),
+            Error::InvalidHeader(header) => write!(f, "invalid header: {}", header),
+            Error::InvalidCompressedData => f.write_str("invalid compressed data"),
+            Error::BufferFull => f.write_str("buffer is too small"),
+            Error::UnknownKey(id) => write!(f, "unknown encryption key id: {}", id),
+            Error::Decrypt => f.write_str("failed to decrypt"),
+            Error::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+// `std::error::Error` with `serde/std`, which any crate in the build may enable
+impl serde::ser::StdError for Error {}
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
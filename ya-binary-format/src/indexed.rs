@@ -0,0 +1,159 @@
+/*!
+Sequences with an offset index, so that single items can be read without the rest.
+
+The layout is a `u32` item count, then the `u32` end offset of every item relative to the
+first one, then the items, each encoded on its own with the given [`Options`].
+All integers are little-endian. Since the header and index sit at known positions,
+a reader holding only a byte range of the value, such as Redis `GETRANGE` returns,
+can locate and decode any slice of the items from the few index entries around it:
+
+```rust
+use ya_binary_format::{indexed, Options};
+
+let options = Options::new();
+let buf = indexed::to_bytes(&options, &["a", "bc", "def"]);
+
+let count = indexed::count(&buf[..indexed::HEADER_SIZE]).unwrap();
+let entries = indexed::Index::entries_range(count, 1..3);
+let index = indexed::Index::parse(count, 1..3, &buf[entries]).unwrap();
+let items: Vec<String> = index.decode(&options, &buf[index.bytes_range()]).unwrap();
+assert_eq!(items, ["bc", "def"]);
+```
+
+Options apply to each item, so with a checksum every item is verified on its own,
+and the dictionary only spans a single item.
+ */
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{error::Error, options::Options};
+
+/// Size of the item count at the start.
+pub const HEADER_SIZE: usize = 4;
+
+/// Encode `items` each with `options`, preceded by the index.
+///
+/// # Panics
+///
+/// If the encoded items exceed 4 GiB.
+pub fn to_bytes<T: Serialize>(options: &Options, items: &[T]) -> Vec<u8> {
+    let mut ends = Vec::with_capacity(items.len());
+    let mut body = Vec::new();
+    for item in items {
+        body.extend_from_slice(&options.to_bytes(item));
+        ends.push(u32::try_from(body.len()).expect("indexed values are limited to 4 GiB"));
+    }
+    let count = u32::try_from(items.len()).expect("indexed values are limited to 4 GiB");
+    let mut buf = Vec::with_capacity(HEADER_SIZE + ends.len() * 4 + body.len());
+    buf.extend_from_slice(&count.to_le_bytes());
+    for end in ends {
+        buf.extend_from_slice(&end.to_le_bytes());
+    }
+    buf.extend_from_slice(&body);
+    buf
+}
+
+/// Decode all items of a value written by [`to_bytes`].
+pub fn from_bytes<T: DeserializeOwned>(options: &Options, b: &[u8]) -> Result<Vec<T>, Error> {
+    let count = count(b.get(..HEADER_SIZE).ok_or(Error::Truncated)?)?;
+    let entries = Index::entries_range(count, 0..count);
+    let index = Index::parse(count, 0..count, b.get(entries).ok_or(Error::Truncated)?)?;
+    let bytes = index.bytes_range();
+    let b = b.get(bytes.start..).ok_or(Error::Truncated)?;
+    if b.len() > bytes.len() {
+        return Err(Error::TrailingBytes(b.len() - bytes.len()));
+    }
+    index.decode(options, b)
+}
+
+/// Number of items given the first [`HEADER_SIZE`] bytes.
+pub fn count(header: &[u8]) -> Result<usize, Error> {
+    let header: [u8; HEADER_SIZE] = header.try_into().map_err(|_| Error::Truncated)?;
+    let count = u32::from_le_bytes(header);
+    usize::try_from(count).map_err(|_| Error::InvalidLength(count as u64))
+}
+
+/// Location of a range of items, read from the entries of the index around them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    count: usize,
+    items: Range<usize>,
+    /// Start of each item in `items` and end of the last, relative to the first item of all
+    bounds: Vec<usize>,
+}
+
+impl Index {
+    /// Byte range of the index entries needed to locate `items` out of `count`.
+    pub fn entries_range(count: usize, items: Range<usize>) -> Range<usize> {
+        let items = clamp(count, items);
+        if items.is_empty() {
+            return HEADER_SIZE..HEADER_SIZE;
+        }
+        let first = items.start.saturating_sub(1);
+        HEADER_SIZE.saturating_add(first.saturating_mul(4))
+            ..HEADER_SIZE.saturating_add(items.end.saturating_mul(4))
+    }
+
+    /// Read the entries in [`Index::entries_range`].
+    pub fn parse(count: usize, items: Range<usize>, b: &[u8]) -> Result<Index, Error> {
+        let items = clamp(count, items);
+        if b.len() != Index::entries_range(count, items.clone()).len() {
+            return Err(Error::Truncated);
+        }
+        let mut bounds = Vec::with_capacity(items.len() + 1);
+        if items.start == 0 && !items.is_empty() {
+            bounds.push(0);
+        }
+        for chunk in b.chunks_exact(4) {
+            let end = u32::from_le_bytes(chunk.try_into().unwrap());
+            let end = usize::try_from(end).map_err(|_| Error::InvalidLength(end as u64))?;
+            if bounds.last().is_some_and(|&last| end < last) {
+                return Err(Error::InvalidLength(end as u64));
+            }
+            bounds.push(end);
+        }
+        Ok(Index {
+            count,
+            items,
+            bounds,
+        })
+    }
+
+    /// The items located, limited to those there are.
+    pub fn items(&self) -> Range<usize> {
+        self.items.clone()
+    }
+
+    /// Byte range of the items in the whole value.
+    pub fn bytes_range(&self) -> Range<usize> {
+        let base = HEADER_SIZE.saturating_add(self.count.saturating_mul(4));
+        match (self.bounds.first(), self.bounds.last()) {
+            (Some(&start), Some(&end)) => base.saturating_add(start)..base.saturating_add(end),
+            _ => base..base,
+        }
+    }
+
+    /// Decode the items from the bytes in [`Index::bytes_range`].
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        options: &Options,
+        b: &[u8],
+    ) -> Result<Vec<T>, Error> {
+        if b.len() != self.bytes_range().len() {
+            return Err(Error::Truncated);
+        }
+        let base = self.bounds.first().copied().unwrap_or(0);
+        self.bounds
+            .windows(2)
+            .map(|w| options.from_bytes(&b[w[0] - base..w[1] - base]))
+            .collect()
+    }
+}
+
+/// `items` limited to those of `count`.
+fn clamp(count: usize, items: Range<usize>) -> Range<usize> {
+    let end = items.end.min(count);
+    items.start.min(end)..end
+}
@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 pub trait Write {
     fn write(&mut self, b: &[u8]);
 }
@@ -8,8 +10,45 @@ impl Write for Vec<u8> {
     }
 }
 
-impl<'a> Write for &'a mut Vec<u8> {
+impl Write for &mut Vec<u8> {
     fn write(&mut self, b: &[u8]) {
         self.extend_from_slice(b);
     }
 }
+
+/// Writer into a fixed buffer, which never allocates.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+    overflowed: bool,
+}
+
+impl<'a> SliceWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> SliceWriter<'a> {
+        SliceWriter {
+            buf,
+            pos: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Number of bytes written, or `None` if the buffer was too small.
+    pub fn finish(self) -> Option<usize> {
+        (!self.overflowed).then_some(self.pos)
+    }
+}
+
+impl Write for SliceWriter<'_> {
+    fn write(&mut self, b: &[u8]) {
+        if self.overflowed {
+            return;
+        }
+        match self.buf.get_mut(self.pos..self.pos + b.len()) {
+            Some(dst) => {
+                dst.copy_from_slice(b);
+                self.pos += b.len();
+            }
+            None => self.overflowed = true,
+        }
+    }
+}
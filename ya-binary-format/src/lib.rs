@@ -1,10 +1,47 @@
+/*!
+Yet another binary format for serde.
+
+## Features
+
+- `std` (default): enable `serde/std`. Without it the crate is `no_std` + `alloc`.
+- `compress` (default): [`Options::compress`] with LZ4.
+- `encrypt`: field and whole-value encryption with ChaCha20-Poly1305.
+  Nonces come from `getrandom`, so the target must be supported by it.
+
+## Untrusted input
+
+Decoding returns an [`Error`] instead of panicking on any input; see [`Deserializer`] for the limits.
+`fuzz/` holds a `cargo fuzz` target which checks this.
+ */
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod bytes;
+mod checksum;
+#[cfg(feature = "compress")]
+mod compress;
 pub mod de;
+pub mod encoding;
+#[cfg(feature = "encrypt")]
+mod encrypt;
+mod error;
+pub mod indexed;
 pub mod io;
 pub mod never;
+mod options;
+pub mod schema;
 pub mod ser;
+pub mod value;
+mod varint;
+pub mod view;
 
 pub use crate::{
     de::{from_bytes, Deserializer},
-    ser::{to_bytes, Serializer},
+    error::Error,
+    options::Options,
+    ser::{to_bytes, to_slice, Serializer},
 };
+
+#[cfg(feature = "encrypt")]
+pub use crate::encrypt::KeyProvider;
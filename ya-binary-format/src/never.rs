@@ -1,4 +1,4 @@
-use std::fmt;
+use core::fmt;
 
 pub enum Never {}
 
@@ -14,7 +14,7 @@ impl fmt::Display for Never {
     }
 }
 
-impl std::error::Error for Never {}
+impl serde::ser::StdError for Never {}
 
 impl serde::ser::Error for Never {
     fn custom<T: fmt::Display>(_msg: T) -> Self {
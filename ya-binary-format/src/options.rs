@@ -0,0 +1,265 @@
+use alloc::{borrow::Cow, vec::Vec};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "encrypt")]
+use crate::encrypt::{self, Encryption, KeyProvider};
+use crate::{checksum, de::Deserializer, error::Error, io::SliceWriter, ser::Serializer};
+
+/// Encoding options.
+///
+/// Values must be decoded with the same options they were encoded with.
+///
+/// ```rust
+/// use ya_binary_format::Options;
+///
+/// const OPTIONS: Options = Options::new().dictionary(true);
+///
+/// let names = vec!["abc", "abc", "abc"];
+/// let buf = OPTIONS.to_bytes(&names);
+/// assert_eq!(OPTIONS.from_bytes::<Vec<String>>(&buf).unwrap(), names);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub(crate) dictionary: bool,
+    pub(crate) packed_flags: bool,
+    pub(crate) checksum: bool,
+    #[cfg(feature = "compress")]
+    pub(crate) compress: bool,
+    #[cfg(feature = "compress")]
+    pub(crate) compress_above: usize,
+    #[cfg(feature = "encrypt")]
+    pub(crate) encryption: Option<Encryption>,
+}
+
+impl Options {
+    /// Threshold in bytes used by [`Options::compress`] unless set by [`Options::compress_above`].
+    #[cfg(feature = "compress")]
+    pub const DEFAULT_COMPRESS_ABOVE: usize = 1024;
+
+    pub const fn new() -> Options {
+        Options {
+            dictionary: false,
+            packed_flags: false,
+            checksum: false,
+            #[cfg(feature = "compress")]
+            compress: false,
+            #[cfg(feature = "compress")]
+            compress_above: Options::DEFAULT_COMPRESS_ABOVE,
+            #[cfg(feature = "encrypt")]
+            encryption: None,
+        }
+    }
+
+    /// Write each distinct string once per value and refer to it by index afterward.
+    pub const fn dictionary(mut self, enable: bool) -> Options {
+        self.dictionary = enable;
+        self
+    }
+
+    /// Collect the `bool`s and `Option` tags of each struct into a bitmap written before its fields.
+    pub const fn packed_flags(mut self, enable: bool) -> Options {
+        self.packed_flags = enable;
+        self
+    }
+
+    /// Append a CRC32C trailer which is verified before decoding.
+    pub const fn checksum(mut self, enable: bool) -> Options {
+        self.checksum = enable;
+        self
+    }
+
+    /// Prepend a header byte and compress values larger than the threshold with LZ4.
+    #[cfg(feature = "compress")]
+    pub const fn compress(mut self, enable: bool) -> Options {
+        self.compress = enable;
+        self
+    }
+
+    /// Enable compression for values larger than `threshold` bytes.
+    #[cfg(feature = "compress")]
+    pub const fn compress_above(mut self, threshold: usize) -> Options {
+        self.compress = true;
+        self.compress_above = threshold;
+        self
+    }
+
+    /// Encrypt the whole value with ChaCha20-Poly1305.
+    #[cfg(feature = "encrypt")]
+    pub const fn encrypt_value(mut self, provider: &'static dyn KeyProvider) -> Options {
+        let fields = match self.encryption {
+            Some(encryption) => encryption.fields,
+            None => None,
+        };
+        self.encryption = Some(Encryption {
+            provider,
+            value: true,
+            fields,
+        });
+        self
+    }
+
+    /// Encrypt the `fields` of the struct named `name` with ChaCha20-Poly1305.
+    ///
+    /// The names are the ones serde passes to `serialize_struct` and `serialize_field`, after any
+    /// `#[serde(rename)]`.
+    ///
+    /// Each field is encoded on its own, so it never shares dictionary entries or flags with the others.
+    #[cfg(feature = "encrypt")]
+    pub const fn encrypt_fields(
+        mut self,
+        provider: &'static dyn KeyProvider,
+        name: &'static str,
+        fields: &'static [&'static str],
+    ) -> Options {
+        let value = match self.encryption {
+            Some(encryption) => encryption.value,
+            None => false,
+        };
+        self.encryption = Some(Encryption {
+            provider,
+            value,
+            fields: Some((name, fields)),
+        });
+        self
+    }
+
+    /// Encode `v`.
+    ///
+    /// # Panics
+    ///
+    /// If [`Options::try_to_bytes`] fails.
+    pub fn to_bytes<V: ?Sized + Serialize>(&self, v: &V) -> Vec<u8> {
+        match self.try_to_bytes(v) {
+            Ok(buf) => buf,
+            Err(e) => panic!("failed to encode the value: {}", e),
+        }
+    }
+
+    /// Encode `v`, or fail like a sequence longer than its `encoding` allows.
+    pub fn try_to_bytes<V: ?Sized + Serialize>(&self, v: &V) -> Result<Vec<u8>, Error> {
+        let mut ser = Serializer::new(Vec::new(), *self);
+        v.serialize(&mut ser)?;
+        let mut buf = ser.into_inner();
+        #[cfg(feature = "compress")]
+        if self.compress {
+            buf = crate::compress::compress(&buf, self.compress_above);
+        }
+        #[cfg(feature = "encrypt")]
+        if let Some(encryption) = self.encryption.filter(|e| e.value) {
+            buf = encrypt::seal(encryption.provider, b"", &buf);
+        }
+        if self.checksum {
+            let crc = checksum::crc32c(&buf);
+            buf.extend_from_slice(&crc.to_le_bytes());
+        }
+        Ok(buf)
+    }
+
+    /// Encode into `buf` and return the number of bytes written.
+    ///
+    /// This does not allocate as long as the lengths of all sequences and maps are known,
+    /// and none of dictionary, packed flags, compression and whole-value encryption is enabled.
+    pub fn to_slice<V: ?Sized + Serialize>(&self, v: &V, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.transforms_value() {
+            let bytes = self.try_to_bytes(v)?;
+            let dst = buf.get_mut(..bytes.len()).ok_or(Error::BufferFull)?;
+            dst.copy_from_slice(&bytes);
+            return Ok(bytes.len());
+        }
+        let mut ser = Serializer::new(SliceWriter::new(buf), *self);
+        v.serialize(&mut ser)?;
+        let n = ser.into_inner().finish().ok_or(Error::BufferFull)?;
+        if !self.checksum {
+            return Ok(n);
+        }
+        let crc = checksum::crc32c(&buf[..n]);
+        let dst = buf
+            .get_mut(n..n + checksum::SIZE)
+            .ok_or(Error::BufferFull)?;
+        dst.copy_from_slice(&crc.to_le_bytes());
+        Ok(n + checksum::SIZE)
+    }
+
+    /// Whether the encoded value is compressed or encrypted as a whole.
+    fn transforms_value(&self) -> bool {
+        #[cfg(feature = "compress")]
+        if self.compress {
+            return true;
+        }
+        #[cfg(feature = "encrypt")]
+        if matches!(self.encryption, Some(e) if e.value) {
+            return true;
+        }
+        false
+    }
+
+    pub fn from_bytes<T>(&self, b: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.decode_with(b, |de| T::deserialize(de))
+    }
+
+    /// Verify, decrypt and decompress the value as needed, then decode it with `f`.
+    pub(crate) fn decode_with<R>(
+        &self,
+        b: &[u8],
+        f: impl FnOnce(&mut Deserializer<'_>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let payload = self.payload(b)?;
+        let mut de = Deserializer::new(&payload, *self);
+        let v = f(&mut de)?;
+        de.end()?;
+        Ok(v)
+    }
+
+    /// The serialized value inside `b`, after verifying, decrypting and decompressing it as needed.
+    pub(crate) fn payload<'a>(&self, b: &'a [u8]) -> Result<Cow<'a, [u8]>, Error> {
+        let b = if self.checksum {
+            let n = b
+                .len()
+                .checked_sub(checksum::SIZE)
+                .ok_or(Error::Truncated)?;
+            let (value, trailer) = b.split_at(n);
+            let expected = u32::from_le_bytes(trailer.try_into().unwrap());
+            let actual = checksum::crc32c(value);
+            if expected != actual {
+                return Err(Error::ChecksumMismatch { expected, actual });
+            }
+            value
+        } else {
+            b
+        };
+        #[allow(unused_mut)]
+        let mut payload = Cow::Borrowed(b);
+        #[cfg(feature = "encrypt")]
+        if let Some(encryption) = self.encryption.filter(|e| e.value) {
+            payload = Cow::Owned(encrypt::open(encryption.provider, b"", b)?);
+        }
+        #[cfg(feature = "compress")]
+        if self.compress {
+            payload = match payload {
+                Cow::Borrowed(b) => crate::compress::decompress(b)?,
+                Cow::Owned(b) => Cow::Owned(crate::compress::decompress(&b)?.into_owned()),
+            };
+        }
+        Ok(payload)
+    }
+
+    pub(crate) fn deserialize<'a, T>(&self, b: &'a [u8]) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        let mut de = Deserializer::new(b, *self);
+        let v = Deserialize::deserialize(&mut de)?;
+        de.end()?;
+        Ok(v)
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options::new()
+    }
+}
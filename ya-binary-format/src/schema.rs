@@ -0,0 +1,192 @@
+/*!
+Structured description of encoded values.
+
+A [`Schema`] describes the layout of a type as the serializer writes it, and a [`ValueSchema`]
+adds the [`Options`] the value is encoded with. Both are serde types,
+so they can be stored next to the data, compared between versions or read by tools.
+
+```rust
+use ya_binary_format::schema::{Field, Fields, Schema};
+
+let schema = Schema::Struct {
+    name: "User".into(),
+    fields: Fields::Named(vec![
+        Field::new("id", Schema::I64),
+        Field::new("name", Schema::Option { item: Box::new(Schema::String) }),
+    ]),
+};
+```
+
+In JSON the same schema reads
+
+```json
+{"type": "struct", "name": "User", "fields": {"named": [
+    {"name": "id", "schema": {"type": "i64"}},
+    {"name": "name", "schema": {"type": "option", "item": {"type": "string"}}}
+]}}
+```
+ */
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use serde::{Deserialize, Serialize};
+
+use crate::options::Options;
+
+/// Layout of a type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Schema {
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    F32,
+    F64,
+    Char,
+    String,
+    /// Byte string written by `serialize_bytes`.
+    Bytes,
+    Unit,
+    Option {
+        item: Box<Schema>,
+    },
+    /// Sequence prefixed by its length.
+    Seq {
+        item: Box<Schema>,
+    },
+    /// Map prefixed by its length.
+    Map {
+        key: Box<Schema>,
+        value: Box<Schema>,
+    },
+    /// Tuple or array, written without a length.
+    Tuple {
+        items: Vec<Schema>,
+    },
+    Struct {
+        name: String,
+        fields: Fields,
+    },
+    Enum {
+        name: String,
+        variants: Vec<Variant>,
+    },
+    /// Sequence of integers written as a byte string by one of [`crate::encoding`].
+    Encoded {
+        encoding: Encoding,
+        schema: Box<Schema>,
+    },
+    /// The innermost enclosing struct or enum named `name`, for recursive types.
+    Ref {
+        name: String,
+    },
+}
+
+/// Fields of a struct or an enum variant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fields {
+    Unit,
+    Newtype(Box<Schema>),
+    Tuple(Vec<Schema>),
+    Named(Vec<Field>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub schema: Schema,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, schema: Schema) -> Field {
+        Field {
+            name: name.into(),
+            schema,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Fields,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<String>, fields: Fields) -> Variant {
+        Variant {
+            name: name.into(),
+            fields,
+        }
+    }
+}
+
+/// Modules of [`crate::encoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    Delta,
+    DeltaOfDelta,
+    Rle,
+}
+
+/// Serializable description of [`Options`], without the keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionsSchema {
+    pub dictionary: bool,
+    pub packed_flags: bool,
+    pub checksum: bool,
+    /// Compression threshold if compression is enabled.
+    pub compress_above: Option<usize>,
+    pub encrypt_value: bool,
+    pub encrypted_fields: Option<EncryptedFields>,
+}
+
+/// Encrypted fields of the struct named `name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedFields {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl From<&Options> for OptionsSchema {
+    fn from(options: &Options) -> OptionsSchema {
+        #[allow(unused_mut)]
+        let mut schema = OptionsSchema {
+            dictionary: options.dictionary,
+            packed_flags: options.packed_flags,
+            checksum: options.checksum,
+            ..OptionsSchema::default()
+        };
+        #[cfg(feature = "compress")]
+        if options.compress {
+            schema.compress_above = Some(options.compress_above);
+        }
+        #[cfg(feature = "encrypt")]
+        if let Some(encryption) = options.encryption {
+            schema.encrypt_value = encryption.value;
+            schema.encrypted_fields = encryption.fields.map(|(name, fields)| EncryptedFields {
+                name: name.into(),
+                fields: fields.iter().map(|&f| f.into()).collect(),
+            });
+        }
+        schema
+    }
+}
+
+/// Layout of a stored value and the options it is encoded with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueSchema {
+    #[serde(default)]
+    pub options: OptionsSchema,
+    pub schema: Schema,
+}
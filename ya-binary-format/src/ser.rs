@@ -1,12 +1,38 @@
-use serde::{
-    ser::{self, Serialize},
-    serde_if_integer128,
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as Map;
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
 };
+use core::mem;
+#[cfg(feature = "std")]
+use std::collections::HashMap as Map;
 
-use crate::{io::Write, never::Never};
+use serde::ser::{self, Serialize};
+
+#[cfg(feature = "encrypt")]
+use crate::encrypt::{self, Encryption};
+use crate::{error::Error, io::Write, options::Options};
 
 pub struct Serializer<W> {
     writer: W,
+    state: State,
+}
+
+/// Per-value state shared with the nested serializers of variable length collections.
+#[derive(Default)]
+struct State {
+    options: Options,
+    strings: Map<String, usize>,
+    /// Flags of the innermost struct in packed flags mode
+    flags: Vec<Vec<bool>>,
+}
+
+pub struct SerializerStruct<'a, W> {
+    serializer: &'a mut Serializer<W>,
+    name: &'static str,
+    body: Option<Serializer<Vec<u8>>>,
 }
 
 pub struct SerializerCollection<'a, W> {
@@ -17,12 +43,61 @@ pub struct SerializerCollection<'a, W> {
 }
 
 impl<W: Write> Serializer<W> {
+    pub(crate) fn new(writer: W, options: Options) -> Serializer<W> {
+        Serializer {
+            writer,
+            state: State {
+                options,
+                ..State::default()
+            },
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Push the flag to the innermost struct if it is collecting flags.
+    fn push_flag(&mut self, v: bool) -> bool {
+        match self.state.flags.last_mut() {
+            Some(flags) => {
+                flags.push(v);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn serialize_field<T>(
+        &mut self,
+        _name: &'static str,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        #[cfg(feature = "encrypt")]
+        if let Some(encryption) = self.state.options.encryption {
+            if encryption.is_encrypted_field(_name, _key) {
+                let mut ser = Serializer::new(Vec::new(), self.state.options);
+                value.serialize(&mut ser)?;
+                let aad = Encryption::field_aad(_name, _key);
+                let sealed = encrypt::seal(encryption.provider, &aad, &ser.writer);
+                self.serialize_len(sealed.len());
+                self.writer.write(&sealed);
+                return Ok(());
+            }
+        }
+        value.serialize(self)
+    }
+
     fn serialize_len(&mut self, v: usize) {
         if v < 254 {
             self.writer.write(&[v as u8])
-        } else if v < (1 << 32) {
+        } else if let Ok(v) = u32::try_from(v) {
             self.writer.write(&[254]);
-            self.writer.write(&(v as u32).to_le_bytes());
+            self.writer.write(&v.to_le_bytes());
         } else {
             self.writer.write(&[255]);
             self.writer.write(&(v as u64).to_le_bytes());
@@ -31,33 +106,38 @@ impl<W: Write> Serializer<W> {
 }
 
 impl<'a, W: Write> SerializerCollection<'a, W> {
-    fn add<T: ?Sized + Serialize>(&mut self, v: &T) {
+    fn add<T: ?Sized + Serialize>(&mut self, v: &T) -> Result<(), Error> {
         if self.fixed {
-            v.serialize(&mut *self.serializer).unwrap();
+            v.serialize(&mut *self.serializer)
         } else {
             self.len += 1;
             let mut ser = Serializer {
                 writer: &mut self.buf,
+                state: mem::take(&mut self.serializer.state),
             };
-            v.serialize(&mut ser).unwrap();
+            let result = v.serialize(&mut ser);
+            self.serializer.state = ser.state;
+            result
         }
     }
 }
 
 impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
     type SerializeSeq = SerializerCollection<'a, W>;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
     type SerializeMap = SerializerCollection<'a, W>;
-    type SerializeStruct = Self;
+    type SerializeStruct = SerializerStruct<'a, W>;
     type SerializeStructVariant = Self;
 
     #[inline]
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        self.writer.write(if v { b"1" } else { b"0" });
+        if !self.push_flag(v) {
+            self.writer.write(if v { b"1" } else { b"0" });
+        }
         Ok(())
     }
 
@@ -85,12 +165,10 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(())
     }
 
-    serde_if_integer128! {
-        #[inline]
-        fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
-            self.writer.write(&v.to_le_bytes());
-            Ok(())
-        }
+    #[inline]
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.writer.write(&v.to_le_bytes());
+        Ok(())
     }
 
     #[inline]
@@ -117,12 +195,10 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         Ok(())
     }
 
-    serde_if_integer128! {
-        #[inline]
-        fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
-            self.writer.write(&v.to_le_bytes());
-            Ok(())
-        }
+    #[inline]
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.writer.write(&v.to_le_bytes());
+        Ok(())
     }
 
     #[inline]
@@ -139,12 +215,22 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
-        self.serialize_str(&v.to_string())
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
     #[inline]
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        self.serialize_len(v.as_bytes().len());
+        if self.state.options.dictionary {
+            // 0 introduces a new string, n refers to the (n - 1)-th one
+            if let Some(&index) = self.state.strings.get(v) {
+                self.serialize_len(index + 1);
+                return Ok(());
+            }
+            let index = self.state.strings.len();
+            self.state.strings.insert(v.to_string(), index);
+            self.serialize_len(0);
+        }
+        self.serialize_len(v.len());
         self.writer.write(v.as_bytes());
         Ok(())
     }
@@ -158,16 +244,20 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        self.writer.write(b"0");
+        if !self.push_flag(false) {
+            self.writer.write(b"0");
+        }
         Ok(())
     }
 
     #[inline]
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.writer.write(b"1");
+        if !self.push_flag(true) {
+            self.writer.write(b"1");
+        }
         value.serialize(self)
     }
 
@@ -193,19 +283,19 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
     #[inline]
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         variant_index: u32,
@@ -213,7 +303,7 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.serialize_len(variant_index as usize);
         value.serialize(self)
@@ -274,10 +364,25 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     #[inline]
     fn serialize_struct(
         self,
-        _name: &'static str,
+        name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Ok(self)
+        let body = if self.state.options.packed_flags {
+            // the body is buffered since the flags are written before it
+            let mut state = mem::take(&mut self.state);
+            state.flags.push(Vec::new());
+            Some(Serializer {
+                writer: Vec::new(),
+                state,
+            })
+        } else {
+            None
+        };
+        Ok(SerializerStruct {
+            serializer: self,
+            name,
+            body,
+        })
     }
 
     #[inline]
@@ -295,14 +400,13 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
 
 impl<'a, W: Write> ser::SerializeSeq for SerializerCollection<'a, W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add(value);
-        Ok(())
+        self.add(value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -314,13 +418,13 @@ impl<'a, W: Write> ser::SerializeSeq for SerializerCollection<'a, W> {
     }
 }
 
-impl<'a, W: Write> ser::SerializeTuple for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeTuple for &mut Serializer<W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -330,13 +434,13 @@ impl<'a, W: Write> ser::SerializeTuple for &'a mut Serializer<W> {
     }
 }
 
-impl<'a, W: Write> ser::SerializeTupleStruct for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeTupleStruct for &mut Serializer<W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -346,13 +450,13 @@ impl<'a, W: Write> ser::SerializeTupleStruct for &'a mut Serializer<W> {
     }
 }
 
-impl<'a, W: Write> ser::SerializeTupleVariant for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeTupleVariant for &mut Serializer<W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -364,22 +468,20 @@ impl<'a, W: Write> ser::SerializeTupleVariant for &'a mut Serializer<W> {
 
 impl<'a, W: Write> ser::SerializeMap for SerializerCollection<'a, W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add(key);
-        Ok(())
+        self.add(key)
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.add(value);
-        Ok(())
+        self.add(value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -391,37 +493,43 @@ impl<'a, W: Write> ser::SerializeMap for SerializerCollection<'a, W> {
     }
 }
 
-impl<'a, W: Write> ser::SerializeStruct for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeStruct for SerializerStruct<'_, W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        match &mut self.body {
+            Some(body) => body.serialize_field(self.name, key, value),
+            None => self.serializer.serialize_field(self.name, key, value),
+        }
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        if let Some(mut body) = self.body {
+            let flags = body.state.flags.pop().unwrap();
+            self.serializer.state = body.state;
+            let mut bitmap = vec![0u8; flags.len().div_ceil(8)];
+            for (i, _) in flags.iter().enumerate().filter(|(_, &f)| f) {
+                bitmap[i / 8] |= 1 << (i % 8);
+            }
+            self.serializer.serialize_len(bitmap.len());
+            self.serializer.writer.write(&bitmap);
+            self.serializer.writer.write(&body.writer);
+        }
         Ok(())
     }
 }
 
-impl<'a, W: Write> ser::SerializeStructVariant for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
     type Ok = ();
-    type Error = Never;
+    type Error = Error;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        _key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -432,7 +540,10 @@ impl<'a, W: Write> ser::SerializeStructVariant for &'a mut Serializer<W> {
 }
 
 pub fn to_bytes<V: ?Sized + Serialize>(v: &V) -> Vec<u8> {
-    let mut ser = Serializer { writer: Vec::new() };
-    v.serialize(&mut ser).unwrap();
-    ser.writer
+    Options::new().to_bytes(v)
+}
+
+/// Encode into `buf` without allocation and return the number of bytes written.
+pub fn to_slice<V: ?Sized + Serialize>(v: &V, buf: &mut [u8]) -> Result<usize, Error> {
+    Options::new().to_slice(v, buf)
 }
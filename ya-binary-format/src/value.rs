@@ -0,0 +1,1139 @@
+/*!
+Decoding and encoding without the Rust type, guided by a [`Schema`].
+
+[`from_bytes`] turns an encoded value into a [`Value`] tree with field names,
+and [`to_bytes`] encodes such a tree back. `Value` implements serde traits the way
+`serde_json::Value` does, so it can be printed or parsed as JSON to inspect and hand-edit values.
+
+Structs become objects, tuples and sequences arrays, `None` and units null.
+As `Some` of a null could not be told apart from `None`, schemas with options of values which may
+be null, like `Option<Option<T>>` or `Option<()>`, are rejected with [`Error::Unsupported`].
+Unit variants are written as their name and the others as an object with the name as the only key,
+like serde_json does by default.
+
+```rust
+use ya_binary_format::{
+    schema::{Field, Fields, Schema},
+    value::{self, Value},
+    Options,
+};
+
+let schema = Schema::Struct {
+    name: "User".into(),
+    fields: Fields::Named(vec![
+        Field::new("id", Schema::I64),
+        Field::new("name", Schema::String),
+    ]),
+};
+let buf = Options::new().to_bytes(&(1i64, "abc"));
+let user = value::from_bytes(&Options::new(), &schema, &buf).unwrap();
+assert_eq!(
+    user,
+    Value::Object(vec![
+        ("id".into(), Value::Int(1)),
+        ("name".into(), Value::String("abc".into())),
+    ])
+);
+assert_eq!(value::to_bytes(&Options::new(), &schema, &user).unwrap(), buf);
+```
+ */
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    de::{Deserializer, MAX_DEPTH},
+    encoding,
+    error::Error,
+    options::Options,
+    schema::{Encoding, Fields, OptionsSchema, Schema, ValueSchema},
+};
+
+/// Dynamically typed value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Signed integer.
+    Int(i128),
+    /// Unsigned integer.
+    UInt(u128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Value>),
+    /// Struct or object, in field order.
+    Object(Vec<(String, Value)>),
+    /// Map whose keys are not all strings.
+    Map(Vec<(Value, Value)>),
+}
+
+/// Decode `b` encoded with `options` as described by `schema`.
+pub fn from_bytes(options: &Options, schema: &Schema, b: &[u8]) -> Result<Value, Error> {
+    options.decode_with(b, |de| Decoder::new(0, false).decode(de, schema))
+}
+
+/// Bytes of a payload read as one item. See [`explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// Path of the item, like `.users[3].name`.
+    pub path: String,
+    /// What the bytes were read as, like `length 3` or `variant 1 (B)`.
+    pub what: String,
+}
+
+/// A value decoded with [`explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explained {
+    pub value: Value,
+    /// The serialized value, after verifying the checksum, decrypting and decompressing.
+    pub payload: Vec<u8>,
+    /// What each part of `payload` was read as, in order.
+    ///
+    /// Booleans and option tags packed into a flags bitmap take no bytes of their own,
+    /// so their spans are empty.
+    pub spans: Vec<Span>,
+}
+
+/// Decode `b` like [`from_bytes`], recording what each part of the payload was read as.
+pub fn explain(options: &Options, schema: &Schema, b: &[u8]) -> Result<Explained, Error> {
+    let payload = options.payload(b)?;
+    let mut de = Deserializer::new(&payload, *options);
+    let mut decoder = Decoder::new(payload.len(), true);
+    let value = decoder.decode(&mut de, schema)?;
+    de.end()?;
+    Ok(Explained {
+        value,
+        payload: payload.to_vec(),
+        spans: decoder.spans.unwrap_or_default(),
+    })
+}
+
+/// Encode `value` with `options` as described by `schema`.
+pub fn to_bytes(options: &Options, schema: &Schema, value: &Value) -> Result<Vec<u8>, Error> {
+    let typed = typed(options, schema, value, &mut Vec::new())?;
+    Ok(options.to_bytes(&typed))
+}
+
+impl OptionsSchema {
+    /// Options described by the schema.
+    ///
+    /// Encrypted values need keys which the schema cannot hold, so they are rejected.
+    pub fn to_options(&self) -> Result<Options, Error> {
+        if self.encrypt_value || self.encrypted_fields.is_some() {
+            return Err(Error::Unsupported("encrypted values need a key provider"));
+        }
+        let options = Options::new()
+            .dictionary(self.dictionary)
+            .packed_flags(self.packed_flags)
+            .checksum(self.checksum);
+        match self.compress_above {
+            #[cfg(feature = "compress")]
+            Some(threshold) => Ok(options.compress_above(threshold)),
+            #[cfg(not(feature = "compress"))]
+            Some(_) => Err(Error::Unsupported("compression is disabled")),
+            None => Ok(options),
+        }
+    }
+}
+
+impl ValueSchema {
+    /// Decode `b` into a [`Value`]. See [`from_bytes`].
+    pub fn decode(&self, b: &[u8]) -> Result<Value, Error> {
+        from_bytes(&self.options.to_options()?, &self.schema, b)
+    }
+
+    /// Encode a [`Value`]. See [`to_bytes`].
+    pub fn encode(&self, value: &Value) -> Result<Vec<u8>, Error> {
+        to_bytes(&self.options.to_options()?, &self.schema, value)
+    }
+
+    /// Decode `b` recording what each part was read as. See [`explain`].
+    pub fn explain(&self, b: &[u8]) -> Result<Explained, Error> {
+        explain(&self.options.to_options()?, &self.schema, b)
+    }
+}
+
+/// The schema a [`Schema::Ref`] refers to among the enclosing ones.
+pub(crate) fn resolve<'s>(refs: &[&'s Schema], name: &str) -> Option<&'s Schema> {
+    refs.iter().rev().copied().find(|s| match s {
+        Schema::Struct { name: n, .. } | Schema::Enum { name: n, .. } => n == name,
+        _ => false,
+    })
+}
+
+/// Reject an `Option` of values which may be [`Value::Null`] themselves, like `Option<()>` or
+/// `Option<Option<T>>`, as `Some` of them could not be told apart from `None`.
+fn check_option_item(item: &Schema, refs: &[&Schema]) -> Result<(), Error> {
+    fn nullable(schema: &Schema, refs: &[&Schema], depth: usize) -> bool {
+        match schema {
+            Schema::Unit
+            | Schema::Option { .. }
+            | Schema::Struct {
+                fields: Fields::Unit,
+                ..
+            } => true,
+            Schema::Struct {
+                fields: Fields::Newtype(item),
+                ..
+            } => nullable(item, refs, depth),
+            // a cycle of newtypes never ends in a value
+            Schema::Ref { name } if depth < MAX_DEPTH => {
+                resolve(refs, name).is_some_and(|target| nullable(target, refs, depth + 1))
+            }
+            _ => false,
+        }
+    }
+    if nullable(item, refs, 0) {
+        return Err(Error::Unsupported(
+            "options of values which may be null themselves are ambiguous",
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn integer_item(schema: &Schema) -> Option<&Schema> {
+    match schema {
+        Schema::Seq { item } => match **item {
+            Schema::I8
+            | Schema::I16
+            | Schema::I32
+            | Schema::I64
+            | Schema::U8
+            | Schema::U16
+            | Schema::U32
+            | Schema::U64 => Some(item),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Walks the payload along a schema, optionally recording what each byte was read as.
+struct Decoder<'s> {
+    /// Enclosing structs and enums, for [`Schema::Ref`]
+    refs: Vec<&'s Schema>,
+    /// Spans read so far, if explaining
+    spans: Option<Vec<Span>>,
+    /// Path of the item being read, kept only if explaining
+    path: String,
+    /// Length of the payload, to tell positions from the bytes left
+    len: usize,
+}
+
+impl<'s> Decoder<'s> {
+    fn new(len: usize, explain: bool) -> Decoder<'s> {
+        Decoder {
+            refs: Vec::new(),
+            spans: explain.then(Vec::new),
+            path: String::new(),
+            len,
+        }
+    }
+
+    /// Read with `f`, recording the bytes it consumed as `what`.
+    fn read<'b, T>(
+        &mut self,
+        de: &mut Deserializer<'b>,
+        f: impl FnOnce(&mut Deserializer<'b>) -> Result<T, Error>,
+        what: impl FnOnce(&T) -> String,
+    ) -> Result<T, Error> {
+        let remaining = de.remaining();
+        let v = f(de)?;
+        self.record(remaining, de, || what(&v));
+        Ok(v)
+    }
+
+    /// Record the bytes read since `remaining` were left.
+    fn record(&mut self, remaining: usize, de: &Deserializer<'_>, what: impl FnOnce() -> String) {
+        if let Some(spans) = &mut self.spans {
+            spans.push(Span {
+                start: self.len - remaining,
+                end: self.len - de.remaining(),
+                path: self.path.clone(),
+                what: what(),
+            });
+        }
+    }
+
+    /// Run `f` with `segment` appended to the path.
+    fn at<R>(&mut self, segment: fmt::Arguments<'_>, f: impl FnOnce(&mut Self) -> R) -> R {
+        let n = self.path.len();
+        if self.spans.is_some() {
+            let _ = fmt::write(&mut self.path, segment);
+        }
+        let r = f(self);
+        self.path.truncate(n);
+        r
+    }
+
+    fn decode(&mut self, de: &mut Deserializer<'_>, schema: &'s Schema) -> Result<Value, Error> {
+        macro_rules! number {
+            ($t:ident, $variant:ident, $wide:ty) => {{
+                let v = self.read(
+                    de,
+                    |de| de.take_array().map($t::from_le_bytes),
+                    |v| format!(concat!(stringify!($t), " {}"), v),
+                )?;
+                Value::$variant(v as $wide)
+            }};
+        }
+        Ok(match schema {
+            Schema::Bool => {
+                Value::Bool(self.read(de, |de| de.get_flag(), |v| format!("bool {}", v))?)
+            }
+            Schema::I8 => number!(i8, Int, i128),
+            Schema::I16 => number!(i16, Int, i128),
+            Schema::I32 => number!(i32, Int, i128),
+            Schema::I64 => number!(i64, Int, i128),
+            Schema::I128 => number!(i128, Int, i128),
+            Schema::U8 => number!(u8, UInt, u128),
+            Schema::U16 => number!(u16, UInt, u128),
+            Schema::U32 => number!(u32, UInt, u128),
+            Schema::U64 => number!(u64, UInt, u128),
+            Schema::U128 => number!(u128, UInt, u128),
+            Schema::F32 => number!(f32, Float, f64),
+            Schema::F64 => number!(f64, Float, f64),
+            Schema::Char => {
+                let c = self.read(de, |de| char::deserialize(de), |c| format!("char {:?}", c))?;
+                Value::String(c.to_string())
+            }
+            Schema::String => {
+                let s = self.read(de, |de| de.get_str(), |s| format!("string {:?}", s))?;
+                Value::String(s.to_string())
+            }
+            Schema::Bytes => {
+                let b = self.read(
+                    de,
+                    |de| {
+                        let n = de.get_len()?;
+                        de.take_slice(n)
+                    },
+                    |b| format!("{} bytes", b.len()),
+                )?;
+                Value::Bytes(b.to_vec())
+            }
+            Schema::Unit => Value::Null,
+            Schema::Option { item } => {
+                check_option_item(item, &self.refs)?;
+                let some = self.read(
+                    de,
+                    |de| de.get_flag(),
+                    |&some| if some { "some" } else { "none" }.into(),
+                )?;
+                if some {
+                    de.nested(|de| self.decode(de, item))?
+                } else {
+                    Value::Null
+                }
+            }
+            Schema::Seq { item } => {
+                let len = self.read(de, get_count, |n| format!("length {}", n))?;
+                de.nested(|de| {
+                    let mut items = Vec::with_capacity(len.min(4096));
+                    for i in 0..len {
+                        items.push(self.at(format_args!("[{}]", i), |d| d.decode(de, item))?);
+                    }
+                    Ok(Value::Array(items))
+                })?
+            }
+            Schema::Map { key, value } => {
+                let len = self.read(de, get_count, |n| format!("length {}", n))?;
+                let entries = de.nested(|de| {
+                    let mut entries = Vec::with_capacity(len.min(4096));
+                    for i in 0..len {
+                        let k = self.at(format_args!("[{}].key", i), |d| d.decode(de, key))?;
+                        let v = self.at(format_args!("[{}].value", i), |d| d.decode(de, value))?;
+                        entries.push((k, v));
+                    }
+                    Ok(entries)
+                })?;
+                map_value(entries)
+            }
+            Schema::Tuple { items } => de.nested(|de| self.decode_items(de, items))?,
+            Schema::Struct { name, fields } => {
+                self.refs.push(schema);
+                let v = de.nested(|de| match fields {
+                    Fields::Unit => Ok(Value::Null),
+                    Fields::Newtype(item) => self.decode(de, item),
+                    Fields::Tuple(items) => self.decode_items(de, items),
+                    Fields::Named(fields) => {
+                        let remaining = de.remaining();
+                        de.begin_struct()?;
+                        if de.remaining() < remaining {
+                            self.record(remaining, de, || "flags".into());
+                        }
+                        let mut object = Vec::with_capacity(fields.len());
+                        for field in fields {
+                            let v = self.at(format_args!(".{}", field.name), |d| {
+                                d.decode_field(de, name, &field.name, &field.schema)
+                            })?;
+                            object.push((field.name.clone(), v));
+                        }
+                        de.end_struct();
+                        Ok(Value::Object(object))
+                    }
+                });
+                self.refs.pop();
+                v?
+            }
+            Schema::Enum { variants, .. } => {
+                let variant = self.read(
+                    de,
+                    |de| {
+                        let n = de.get_len()?;
+                        variants
+                            .get(n)
+                            .ok_or(Error::UnknownVariant(n))
+                            .map(|v| (n, v))
+                    },
+                    |(n, v)| format!("variant {} ({})", n, v.name),
+                )?;
+                let variant = variant.1;
+                self.refs.push(schema);
+                let v = de.nested(|de| {
+                    let v = match &variant.fields {
+                        Fields::Unit => return Ok(Value::String(variant.name.clone())),
+                        Fields::Newtype(item) => {
+                            self.at(format_args!(".{}", variant.name), |d| d.decode(de, item))?
+                        }
+                        Fields::Tuple(items) => self
+                            .at(format_args!(".{}", variant.name), |d| {
+                                d.decode_items(de, items)
+                            })?,
+                        Fields::Named(fields) => {
+                            let mut object = Vec::with_capacity(fields.len());
+                            for f in fields {
+                                let v = self
+                                    .at(format_args!(".{}.{}", variant.name, f.name), |d| {
+                                        d.decode(de, &f.schema)
+                                    })?;
+                                object.push((f.name.clone(), v));
+                            }
+                            Value::Object(object)
+                        }
+                    };
+                    Ok(Value::Object(alloc::vec![(variant.name.clone(), v)]))
+                });
+                self.refs.pop();
+                v?
+            }
+            Schema::Encoded { encoding, schema } => {
+                let item = integer_item(schema).ok_or(Error::Unsupported(
+                    "encoded sequences must be of integers of up to 64 bits",
+                ))?;
+                self.read(
+                    de,
+                    |de| decode_encoded(de, *encoding, item),
+                    |v| match v {
+                        Value::Array(items) => {
+                            format!("{:?} encoded, {} items", encoding, items.len())
+                        }
+                        _ => format!("{:?} encoded", encoding),
+                    },
+                )?
+            }
+            Schema::Ref { name } => {
+                let target = resolve(&self.refs, name)
+                    .ok_or(Error::Unsupported("unresolved schema reference"))?;
+                self.decode(de, target)?
+            }
+        })
+    }
+
+    /// Decode the field `field` of the struct `name`, which may be encrypted.
+    fn decode_field(
+        &mut self,
+        de: &mut Deserializer<'_>,
+        name: &str,
+        field: &str,
+        schema: &'s Schema,
+    ) -> Result<Value, Error> {
+        #[cfg(feature = "encrypt")]
+        if let Some(e) = de
+            .field_encryption(name)
+            .filter(|e| e.is_encrypted_field(name, field))
+        {
+            // The bytes inside are not in the payload, so the field is recorded as a whole.
+            let mut inner = Decoder::new(0, false);
+            inner.refs = core::mem::take(&mut self.refs);
+            let v = self.read(
+                de,
+                |de| de.decode_encrypted(&e, name, field, |de| inner.decode(de, schema)),
+                |_| "encrypted".into(),
+            );
+            self.refs = inner.refs;
+            return v;
+        }
+        let _ = (name, field);
+        self.decode(de, schema)
+    }
+
+    fn decode_items(
+        &mut self,
+        de: &mut Deserializer<'_>,
+        items: &'s [Schema],
+    ) -> Result<Value, Error> {
+        let mut values = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            values.push(self.at(format_args!("[{}]", i), |d| d.decode(de, item))?);
+        }
+        Ok(Value::Array(values))
+    }
+}
+
+/// Number of items of a sequence or map.
+///
+/// Items of types like `()` take no bytes, so unlike the serde path, which does not allocate for them,
+/// more items than bytes left are only accepted up to a small bound.
+pub(crate) fn get_count(de: &mut Deserializer<'_>) -> Result<usize, Error> {
+    let len = de.get_len()?;
+    if len > de.remaining().max(4096) {
+        return Err(Error::TooLarge);
+    }
+    Ok(len)
+}
+
+pub(crate) fn decode_encoded(
+    de: &mut Deserializer<'_>,
+    encoding: Encoding,
+    item: &Schema,
+) -> Result<Value, Error> {
+    macro_rules! decode_as {
+        ($t:ty, $variant:ident, $wide:ty) => {{
+            let v: Vec<$t> = match encoding {
+                Encoding::Delta => encoding::delta::deserialize(&mut *de)?,
+                Encoding::DeltaOfDelta => encoding::delta_of_delta::deserialize(&mut *de)?,
+                Encoding::Rle => encoding::rle::deserialize(&mut *de)?,
+            };
+            Value::Array(v.into_iter().map(|x| Value::$variant(x as $wide)).collect())
+        }};
+    }
+    Ok(match item {
+        Schema::I8 => decode_as!(i8, Int, i128),
+        Schema::I16 => decode_as!(i16, Int, i128),
+        Schema::I32 => decode_as!(i32, Int, i128),
+        Schema::I64 => decode_as!(i64, Int, i128),
+        Schema::U8 => decode_as!(u8, UInt, u128),
+        Schema::U16 => decode_as!(u16, UInt, u128),
+        Schema::U32 => decode_as!(u32, UInt, u128),
+        _ => decode_as!(u64, UInt, u128),
+    })
+}
+
+/// An object if all keys are strings.
+fn map_value(entries: Vec<(Value, Value)>) -> Value {
+    if entries.iter().all(|(k, _)| matches!(k, Value::String(_))) {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| match k {
+                    Value::String(k) => (k, v),
+                    _ => unreachable!(),
+                })
+                .collect(),
+        )
+    } else {
+        Value::Map(entries)
+    }
+}
+
+/// Value checked against its schema, which can be serialized without failing.
+enum Typed {
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+    Unit,
+    None,
+    Some(Box<Typed>),
+    Seq(Vec<Typed>),
+    Map(Vec<(Typed, Typed)>),
+    Tuple(Vec<Typed>),
+    Struct(&'static str, Vec<(&'static str, Typed)>),
+    UnitStruct,
+    NewtypeStruct(Box<Typed>),
+    TupleStruct(Vec<Typed>),
+    UnitVariant(u32),
+    NewtypeVariant(u32, Box<Typed>),
+    TupleVariant(u32, Vec<Typed>),
+    StructVariant(u32, Vec<Typed>),
+    Signed(Encoding, Vec<i64>),
+    Unsigned(Encoding, Vec<u64>),
+}
+
+/// The value does not match the schema.
+fn mismatch(expected: &'static str) -> Error {
+    Error::Mismatch {
+        path: String::new(),
+        expected,
+    }
+}
+
+/// Prepend `segment` to the path of a mismatch found below it.
+fn at(segment: impl fmt::Display) -> impl FnOnce(Error) -> Error {
+    move |e| match e {
+        Error::Mismatch { path, expected } => Error::Mismatch {
+            path: format!("{}{}", segment, path),
+            expected,
+        },
+        e => e,
+    }
+}
+
+/// Names given to the serializer. Only encrypted fields need theirs, which the options hold as `'static`.
+fn static_names(options: &Options, name: &str) -> (&'static str, &'static [&'static str]) {
+    #[cfg(feature = "encrypt")]
+    if let Some((n, fields)) = options.encryption.and_then(|e| e.fields) {
+        if n == name {
+            return (n, fields);
+        }
+    }
+    let _ = (options, name);
+    ("", &[])
+}
+
+fn typed<'s>(
+    options: &Options,
+    schema: &'s Schema,
+    value: &Value,
+    refs: &mut Vec<&'s Schema>,
+) -> Result<Typed, Error> {
+    macro_rules! int {
+        ($t:ident, $variant:ident) => {
+            match *value {
+                Value::Int(v) => $t::try_from(v).ok(),
+                Value::UInt(v) => $t::try_from(v).ok(),
+                _ => None,
+            }
+            .map(Typed::$variant)
+            .ok_or_else(|| mismatch(stringify!($t)))?
+        };
+    }
+    Ok(match schema {
+        Schema::Bool => match *value {
+            Value::Bool(v) => Typed::Bool(v),
+            _ => return Err(mismatch("bool")),
+        },
+        Schema::I8 => int!(i8, I8),
+        Schema::I16 => int!(i16, I16),
+        Schema::I32 => int!(i32, I32),
+        Schema::I64 => int!(i64, I64),
+        Schema::I128 => int!(i128, I128),
+        Schema::U8 => int!(u8, U8),
+        Schema::U16 => int!(u16, U16),
+        Schema::U32 => int!(u32, U32),
+        Schema::U64 => int!(u64, U64),
+        Schema::U128 => int!(u128, U128),
+        Schema::F32 => Typed::F32(float(value)? as f32),
+        Schema::F64 => Typed::F64(float(value)?),
+        Schema::Char => match value {
+            Value::String(s) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Typed::Char(c),
+                    _ => return Err(mismatch("char")),
+                }
+            }
+            _ => return Err(mismatch("char")),
+        },
+        Schema::String => match value {
+            Value::String(s) => Typed::String(s.clone()),
+            _ => return Err(mismatch("string")),
+        },
+        Schema::Bytes => Typed::Bytes(bytes(value)?),
+        Schema::Unit => match value {
+            Value::Null => Typed::Unit,
+            _ => return Err(mismatch("null")),
+        },
+        Schema::Option { item } => {
+            check_option_item(item, refs)?;
+            match value {
+                Value::Null => Typed::None,
+                v => Typed::Some(Box::new(typed(options, item, v, refs)?)),
+            }
+        }
+        Schema::Seq { item } => Typed::Seq(typed_items(
+            options,
+            core::iter::repeat(&**item),
+            array(value)?,
+            refs,
+        )?),
+        Schema::Map {
+            key,
+            value: value_schema,
+        } => {
+            let entries = match value {
+                Value::Map(entries) => entries
+                    .iter()
+                    .map(|(k, v)| Ok((typed(options, key, k, refs)?, v)))
+                    .collect::<Result<Vec<_>, Error>>()?,
+                Value::Object(entries) => entries
+                    .iter()
+                    .map(|(k, v)| {
+                        let k = parse_key(key, k).map_err(at(format_args!(".{}", k)))?;
+                        Ok((typed(options, key, &k, refs)?, v))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?,
+                _ => return Err(mismatch("map")),
+            };
+            let mut map = Vec::with_capacity(entries.len());
+            for (i, (k, value)) in entries.into_iter().enumerate() {
+                let value = typed(options, value_schema, value, refs)
+                    .map_err(at(format_args!("[{}]", i)))?;
+                map.push((k, value));
+            }
+            Typed::Map(map)
+        }
+        Schema::Tuple { items } => Typed::Tuple(typed_tuple(options, items, value, refs)?),
+        Schema::Struct { name, fields } => {
+            refs.push(schema);
+            let v = match fields {
+                Fields::Unit => match value {
+                    Value::Null => Ok(Typed::UnitStruct),
+                    _ => Err(mismatch("null")),
+                },
+                Fields::Newtype(item) => {
+                    typed(options, item, value, refs).map(|v| Typed::NewtypeStruct(Box::new(v)))
+                }
+                Fields::Tuple(items) => {
+                    typed_tuple(options, items, value, refs).map(Typed::TupleStruct)
+                }
+                Fields::Named(fields) => {
+                    let (name, names) = static_names(options, name);
+                    typed_fields(options, fields, value, refs).map(|values| {
+                        Typed::Struct(
+                            name,
+                            fields
+                                .iter()
+                                .zip(values)
+                                .map(|(f, v)| {
+                                    (
+                                        names.iter().copied().find(|n| *n == f.name).unwrap_or(""),
+                                        v,
+                                    )
+                                })
+                                .collect(),
+                        )
+                    })
+                }
+            };
+            refs.pop();
+            v?
+        }
+        Schema::Enum { variants, .. } => {
+            let (name, content) = match value {
+                Value::String(name) => (name, None),
+                Value::Object(entries) if entries.len() == 1 => {
+                    (&entries[0].0, Some(&entries[0].1))
+                }
+                _ => return Err(mismatch("variant name or object with a single variant")),
+            };
+            let index = variants
+                .iter()
+                .position(|v| v.name == *name)
+                .ok_or_else(|| mismatch("known variant"))?;
+            let fields = &variants[index].fields;
+            let index = index as u32;
+            refs.push(schema);
+            let v =
+                match (fields, content) {
+                    (Fields::Unit, None | Some(Value::Null)) => Ok(Typed::UnitVariant(index)),
+                    (Fields::Unit, Some(_)) => Err(mismatch("unit variant")),
+                    (_, None) => Err(mismatch("variant with content")),
+                    (Fields::Newtype(item), Some(v)) => typed(options, item, v, refs)
+                        .map(|v| Typed::NewtypeVariant(index, Box::new(v))),
+                    (Fields::Tuple(items), Some(v)) => {
+                        typed_tuple(options, items, v, refs).map(|v| Typed::TupleVariant(index, v))
+                    }
+                    (Fields::Named(fields), Some(v)) => typed_fields(options, fields, v, refs)
+                        .map(|v| Typed::StructVariant(index, v)),
+                }
+                .map_err(at(format_args!(".{}", name)));
+            refs.pop();
+            v?
+        }
+        Schema::Encoded { encoding, schema } => {
+            let item = integer_item(schema).ok_or(Error::Unsupported(
+                "encoded sequences must be of integers of up to 64 bits",
+            ))?;
+            let items = typed_items(options, core::iter::repeat(item), array(value)?, refs)?;
+            if matches!(item, Schema::U8 | Schema::U16 | Schema::U32 | Schema::U64) {
+                Typed::Unsigned(*encoding, items.iter().map(unsigned).collect())
+            } else {
+                Typed::Signed(*encoding, items.iter().map(signed).collect())
+            }
+        }
+        Schema::Ref { name } => {
+            let target =
+                resolve(refs, name).ok_or(Error::Unsupported("unresolved schema reference"))?;
+            typed(options, target, value, refs)?
+        }
+    })
+}
+
+fn float(value: &Value) -> Result<f64, Error> {
+    match *value {
+        Value::Float(v) => Ok(v),
+        Value::Int(v) => Ok(v as f64),
+        Value::UInt(v) => Ok(v as f64),
+        _ => Err(mismatch("number")),
+    }
+}
+
+/// Bytes, or an array of bytes as JSON has no byte strings.
+fn bytes(value: &Value) -> Result<Vec<u8>, Error> {
+    match value {
+        Value::Bytes(b) => Ok(b.clone()),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| match *v {
+                Value::Int(b) => u8::try_from(b).ok(),
+                Value::UInt(b) => u8::try_from(b).ok(),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| mismatch("bytes")),
+        _ => Err(mismatch("bytes")),
+    }
+}
+
+fn array(value: &Value) -> Result<&[Value], Error> {
+    match value {
+        Value::Array(items) => Ok(items),
+        _ => Err(mismatch("array")),
+    }
+}
+
+fn signed(v: &Typed) -> i64 {
+    match *v {
+        Typed::I8(v) => v as i64,
+        Typed::I16(v) => v as i64,
+        Typed::I32(v) => v as i64,
+        Typed::I64(v) => v,
+        _ => 0,
+    }
+}
+
+fn unsigned(v: &Typed) -> u64 {
+    match *v {
+        Typed::U8(v) => v as u64,
+        Typed::U16(v) => v as u64,
+        Typed::U32(v) => v as u64,
+        Typed::U64(v) => v,
+        _ => 0,
+    }
+}
+
+/// Map keys are strings in JSON objects, so parse them as the key schema wants.
+fn parse_key(schema: &Schema, key: &str) -> Result<Value, Error> {
+    let parsed = match schema {
+        Schema::Bool => key.parse().ok().map(Value::Bool),
+        Schema::I8 | Schema::I16 | Schema::I32 | Schema::I64 | Schema::I128 => {
+            key.parse().ok().map(Value::Int)
+        }
+        Schema::U8 | Schema::U16 | Schema::U32 | Schema::U64 | Schema::U128 => {
+            key.parse().ok().map(Value::UInt)
+        }
+        Schema::F32 | Schema::F64 => key.parse().ok().map(Value::Float),
+        _ => Some(Value::String(key.to_string())),
+    };
+    parsed.ok_or_else(|| mismatch("key of the map's key type"))
+}
+
+fn typed_items<'s>(
+    options: &Options,
+    schemas: impl Iterator<Item = &'s Schema>,
+    values: &[Value],
+    refs: &mut Vec<&'s Schema>,
+) -> Result<Vec<Typed>, Error> {
+    schemas
+        .zip(values)
+        .enumerate()
+        .map(|(i, (schema, v))| {
+            typed(options, schema, v, refs).map_err(at(format_args!("[{}]", i)))
+        })
+        .collect()
+}
+
+fn typed_tuple<'s>(
+    options: &Options,
+    items: &'s [Schema],
+    value: &Value,
+    refs: &mut Vec<&'s Schema>,
+) -> Result<Vec<Typed>, Error> {
+    let values = array(value)?;
+    if values.len() != items.len() {
+        return Err(mismatch("array of the tuple's length"));
+    }
+    typed_items(options, items.iter(), values, refs)
+}
+
+/// Values of the named fields in schema order. Missing optional fields are `None`.
+fn typed_fields<'s>(
+    options: &Options,
+    fields: &'s [crate::schema::Field],
+    value: &Value,
+    refs: &mut Vec<&'s Schema>,
+) -> Result<Vec<Typed>, Error> {
+    let entries = match value {
+        Value::Object(entries) => entries,
+        _ => return Err(mismatch("object")),
+    };
+    if let Some((k, _)) = entries
+        .iter()
+        .find(|(k, _)| !fields.iter().any(|f| f.name == *k))
+    {
+        return Err(mismatch("known field")).map_err(at(format_args!(".{}", k)));
+    }
+    fields
+        .iter()
+        .map(|f| {
+            let v = entries.iter().find(|(k, _)| *k == f.name).map(|(_, v)| v);
+            match (v, &f.schema) {
+                (Some(v), schema) => typed(options, schema, v, refs),
+                (None, Schema::Option { .. }) => Ok(Typed::None),
+                (None, _) => Err(mismatch("field")),
+            }
+            .map_err(at(format_args!(".{}", f.name)))
+        })
+        .collect()
+}
+
+impl Serialize for Typed {
+    fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Typed::Bool(v) => s.serialize_bool(*v),
+            Typed::I8(v) => s.serialize_i8(*v),
+            Typed::I16(v) => s.serialize_i16(*v),
+            Typed::I32(v) => s.serialize_i32(*v),
+            Typed::I64(v) => s.serialize_i64(*v),
+            Typed::I128(v) => s.serialize_i128(*v),
+            Typed::U8(v) => s.serialize_u8(*v),
+            Typed::U16(v) => s.serialize_u16(*v),
+            Typed::U32(v) => s.serialize_u32(*v),
+            Typed::U64(v) => s.serialize_u64(*v),
+            Typed::U128(v) => s.serialize_u128(*v),
+            Typed::F32(v) => s.serialize_f32(*v),
+            Typed::F64(v) => s.serialize_f64(*v),
+            Typed::Char(v) => s.serialize_char(*v),
+            Typed::String(v) => s.serialize_str(v),
+            Typed::Bytes(v) => s.serialize_bytes(v),
+            Typed::Unit => s.serialize_unit(),
+            Typed::None => s.serialize_none(),
+            Typed::Some(v) => s.serialize_some(v),
+            Typed::Seq(items) => {
+                let mut seq = s.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Typed::Map(entries) => {
+                let mut map = s.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Typed::Tuple(items) => {
+                let mut tuple = s.serialize_tuple(items.len())?;
+                for item in items {
+                    tuple.serialize_element(item)?;
+                }
+                tuple.end()
+            }
+            Typed::Struct(name, fields) => {
+                let mut st = s.serialize_struct(name, fields.len())?;
+                for (key, v) in fields {
+                    st.serialize_field(key, v)?;
+                }
+                st.end()
+            }
+            Typed::UnitStruct => s.serialize_unit_struct(""),
+            Typed::NewtypeStruct(v) => s.serialize_newtype_struct("", v),
+            Typed::TupleStruct(items) => {
+                let mut tuple = s.serialize_tuple_struct("", items.len())?;
+                for item in items {
+                    ser::SerializeTupleStruct::serialize_field(&mut tuple, item)?;
+                }
+                ser::SerializeTupleStruct::end(tuple)
+            }
+            Typed::UnitVariant(index) => s.serialize_unit_variant("", *index, ""),
+            Typed::NewtypeVariant(index, v) => s.serialize_newtype_variant("", *index, "", v),
+            Typed::TupleVariant(index, items) => {
+                let mut tuple = s.serialize_tuple_variant("", *index, "", items.len())?;
+                for item in items {
+                    ser::SerializeTupleVariant::serialize_field(&mut tuple, item)?;
+                }
+                ser::SerializeTupleVariant::end(tuple)
+            }
+            Typed::StructVariant(index, fields) => {
+                let mut st = s.serialize_struct_variant("", *index, "", fields.len())?;
+                for v in fields {
+                    ser::SerializeStructVariant::serialize_field(&mut st, "", v)?;
+                }
+                ser::SerializeStructVariant::end(st)
+            }
+            Typed::Signed(encoding, v) => match encoding {
+                Encoding::Delta => encoding::delta::serialize(v, s),
+                Encoding::DeltaOfDelta => encoding::delta_of_delta::serialize(v, s),
+                Encoding::Rle => encoding::rle::serialize(v, s),
+            },
+            Typed::Unsigned(encoding, v) => match encoding {
+                Encoding::Delta => encoding::delta::serialize(v, s),
+                Encoding::DeltaOfDelta => encoding::delta_of_delta::serialize(v, s),
+                Encoding::Rle => encoding::rle::serialize(v, s),
+            },
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => s.serialize_unit(),
+            Value::Bool(v) => s.serialize_bool(*v),
+            Value::Int(v) => match i64::try_from(*v) {
+                Ok(v) => s.serialize_i64(v),
+                Err(_) => s.serialize_i128(*v),
+            },
+            Value::UInt(v) => match u64::try_from(*v) {
+                Ok(v) => s.serialize_u64(v),
+                Err(_) => s.serialize_u128(*v),
+            },
+            Value::Float(v) => s.serialize_f64(*v),
+            Value::String(v) => s.serialize_str(v),
+            Value::Bytes(v) => s.serialize_bytes(v),
+            Value::Array(items) => {
+                let mut seq = s.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Object(entries) => {
+                let mut map = s.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Map(entries) => {
+                let mut map = s.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v as i128))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::UInt(v as u128))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Value, E> {
+        Ok(Value::UInt(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, d: D) -> Result<Value, D::Error> {
+        d.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(map_value(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Value, D::Error> {
+        d.deserialize_any(ValueVisitor)
+    }
+}
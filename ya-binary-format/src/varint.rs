@@ -0,0 +1,31 @@
+use alloc::vec::Vec;
+
+pub(crate) fn write_u64(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+pub(crate) fn write_i64(buf: &mut Vec<u8>, v: i64) {
+    write_u64(buf, ((v << 1) ^ (v >> 63)) as u64);
+}
+
+pub(crate) fn read_u64(data: &mut &[u8]) -> Option<u64> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&b, rest) = data.split_first()?;
+        *data = rest;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b < 0x80 {
+            return Some(v);
+        }
+    }
+    None
+}
+
+pub(crate) fn read_i64(data: &mut &[u8]) -> Option<i64> {
+    let v = read_u64(data)?;
+    Some((v >> 1) as i64 ^ -((v & 1) as i64))
+}
@@ -0,0 +1,252 @@
+/*!
+Lazy access to the fields of an encoded struct.
+
+A [`View`] keeps the serialized bytes of a struct and decodes a single field when asked,
+skipping the fields before it as described by the struct's [`Schema`].
+Skipping reads only lengths, tags and the dictionary, so reading a few fields of a large value
+costs far less than decoding all of it.
+
+```rust
+use serde::Serialize;
+use ya_binary_format::{
+    schema::{Field, Fields, Schema},
+    view::View,
+    Options,
+};
+
+#[derive(Serialize)]
+struct User {
+    tags: Vec<String>,
+    score: u64,
+}
+
+let schema = Schema::Struct {
+    name: "User".into(),
+    fields: Fields::Named(vec![
+        Field::new("tags", Schema::Seq { item: Box::new(Schema::String) }),
+        Field::new("score", Schema::U64),
+    ]),
+};
+let buf = Options::new().to_bytes(&User { tags: vec!["a".into(); 1000], score: 7 });
+let view = View::new(&Options::new(), &buf).unwrap();
+assert_eq!(view.field::<u64>(&schema, "score").unwrap(), 7);
+```
+ */
+use alloc::{borrow::Cow, vec, vec::Vec};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    de::Deserializer,
+    error::Error,
+    options::Options,
+    schema::{Fields, Schema},
+    value::{decode_encoded, get_count, integer_item, resolve},
+};
+
+/// Encoded struct whose fields are decoded on demand.
+#[derive(Debug, Clone)]
+pub struct View<'a> {
+    payload: Cow<'a, [u8]>,
+    options: Options,
+}
+
+impl<'a> View<'a> {
+    /// Verify, decrypt and decompress `b` as needed, leaving the fields to be decoded later.
+    pub fn new(options: &Options, b: &'a [u8]) -> Result<View<'a>, Error> {
+        Ok(View {
+            payload: options.payload(b)?,
+            options: *options,
+        })
+    }
+
+    /// Like [`View::new`], taking ownership of the bytes to avoid copying them.
+    pub fn from_vec(options: &Options, mut b: Vec<u8>) -> Result<View<'static>, Error> {
+        let payload = match options.payload(&b)? {
+            Cow::Owned(payload) => payload,
+            // only a trailer is cut off when nothing is decrypted or decompressed
+            Cow::Borrowed(payload) => {
+                b.truncate(payload.len());
+                b
+            }
+        };
+        Ok(View {
+            payload: Cow::Owned(payload),
+            options: *options,
+        })
+    }
+
+    /// The serialized struct.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Decode the field named `field` of the struct described by `schema`.
+    pub fn field<T: DeserializeOwned>(&self, schema: &Schema, field: &str) -> Result<T, Error> {
+        self.field_with(schema, field, |de| T::deserialize(de))
+    }
+
+    /// Decode the field named `field` with `f`, for fields using `#[serde(with = "...")]`.
+    pub fn field_with<T>(
+        &self,
+        schema: &Schema,
+        field: &str,
+        f: impl FnOnce(&mut Deserializer<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let (_name, fields) = match schema {
+            Schema::Struct {
+                name,
+                fields: Fields::Named(fields),
+            } => (name, fields),
+            _ => return Err(Error::Unsupported("views need a struct with named fields")),
+        };
+        let mut de = Deserializer::new(&self.payload, self.options);
+        let mut refs = vec![schema];
+        de.nested(|de| {
+            de.begin_struct()?;
+            #[cfg(feature = "encrypt")]
+            let encryption = de.field_encryption(_name);
+            for f_schema in fields {
+                #[cfg(feature = "encrypt")]
+                if let Some(e) = encryption.filter(|e| e.is_encrypted_field(_name, &f_schema.name))
+                {
+                    if f_schema.name == field {
+                        return de.decode_encrypted(&e, _name, field, f);
+                    }
+                    let n = de.get_len()?;
+                    de.take_slice(n)?;
+                    continue;
+                }
+                if f_schema.name == field {
+                    return f(de);
+                }
+                skip(de, &f_schema.schema, &mut refs)?;
+            }
+            Err(Error::Unsupported("no such field in the schema"))
+        })
+    }
+
+    /// A view which owns its bytes.
+    pub fn into_owned(self) -> View<'static> {
+        View {
+            payload: Cow::Owned(self.payload.into_owned()),
+            options: self.options,
+        }
+    }
+}
+
+/// Read past a value described by `schema`, keeping the dictionary and flags in step.
+fn skip<'s>(
+    de: &mut Deserializer<'_>,
+    schema: &'s Schema,
+    refs: &mut Vec<&'s Schema>,
+) -> Result<(), Error> {
+    match schema {
+        Schema::Bool => {
+            de.get_flag()?;
+        }
+        Schema::I8 | Schema::U8 => {
+            de.take_slice(1)?;
+        }
+        Schema::I16 | Schema::U16 => {
+            de.take_slice(2)?;
+        }
+        Schema::I32 | Schema::U32 | Schema::F32 => {
+            de.take_slice(4)?;
+        }
+        Schema::I64 | Schema::U64 | Schema::F64 => {
+            de.take_slice(8)?;
+        }
+        Schema::I128 | Schema::U128 => {
+            de.take_slice(16)?;
+        }
+        Schema::Char | Schema::String => {
+            de.get_str()?;
+        }
+        Schema::Bytes => {
+            let n = de.get_len()?;
+            de.take_slice(n)?;
+        }
+        Schema::Unit => {}
+        Schema::Option { item } => {
+            if de.get_flag()? {
+                de.nested(|de| skip(de, item, refs))?;
+            }
+        }
+        Schema::Seq { item } => {
+            let len = get_count(de)?;
+            de.nested(|de| (0..len).try_for_each(|_| skip(de, item, refs)))?;
+        }
+        Schema::Map { key, value } => {
+            let len = get_count(de)?;
+            de.nested(|de| {
+                (0..len).try_for_each(|_| {
+                    skip(de, key, refs)?;
+                    skip(de, value, refs)
+                })
+            })?;
+        }
+        Schema::Tuple { items } => {
+            de.nested(|de| items.iter().try_for_each(|item| skip(de, item, refs)))?;
+        }
+        Schema::Struct { name, fields } => {
+            refs.push(schema);
+            let r = de.nested(|de| match fields {
+                Fields::Named(fields) => {
+                    de.begin_struct()?;
+                    #[cfg(feature = "encrypt")]
+                    let encryption = de.field_encryption(name);
+                    for field in fields {
+                        #[cfg(feature = "encrypt")]
+                        if encryption.is_some_and(|e| e.is_encrypted_field(name, &field.name)) {
+                            let n = de.get_len()?;
+                            de.take_slice(n)?;
+                            continue;
+                        }
+                        skip(de, &field.schema, refs)?;
+                    }
+                    de.end_struct();
+                    let _ = name;
+                    Ok(())
+                }
+                fields => skip_fields(de, fields, refs),
+            });
+            refs.pop();
+            r?;
+        }
+        Schema::Enum { variants, .. } => {
+            let n = de.get_len()?;
+            let variant = variants.get(n).ok_or(Error::UnknownVariant(n))?;
+            refs.push(schema);
+            let r = de.nested(|de| skip_fields(de, &variant.fields, refs));
+            refs.pop();
+            r?;
+        }
+        Schema::Encoded { encoding, schema } => {
+            let item = integer_item(schema).ok_or(Error::Unsupported(
+                "encoded sequences must be of integers of up to 64 bits",
+            ))?;
+            decode_encoded(de, *encoding, item)?;
+        }
+        Schema::Ref { name } => {
+            let target =
+                resolve(refs, name).ok_or(Error::Unsupported("unresolved schema reference"))?;
+            skip(de, target, refs)?;
+        }
+    }
+    Ok(())
+}
+
+/// Skip fields written without a flags bitmap, as those of variants and unnamed structs.
+fn skip_fields<'s>(
+    de: &mut Deserializer<'_>,
+    fields: &'s Fields,
+    refs: &mut Vec<&'s Schema>,
+) -> Result<(), Error> {
+    match fields {
+        Fields::Unit => Ok(()),
+        Fields::Newtype(item) => skip(de, item, refs),
+        Fields::Tuple(items) => items.iter().try_for_each(|item| skip(de, item, refs)),
+        Fields::Named(fields) => fields.iter().try_for_each(|f| skip(de, &f.schema, refs)),
+    }
+}
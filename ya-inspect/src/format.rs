@@ -0,0 +1,188 @@
+//! Textual forms of encoded values.
+use std::{fmt::Write, str::FromStr};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// How encoded bytes are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Hex digits, whitespace and an optional `0x` are ignored.
+    Hex,
+    /// Standard base64, whitespace and padding are ignored.
+    Base64,
+    /// The bytes themselves.
+    Raw,
+    /// Output of `redis-cli`: a quoted string with escapes, or with `--raw`
+    /// the bytes followed by a newline.
+    RedisCli,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "hex" => Ok(Format::Hex),
+            "base64" => Ok(Format::Base64),
+            "raw" => Ok(Format::Raw),
+            "redis-cli" => Ok(Format::RedisCli),
+            _ => Err(format!("unknown format `{}`", s)),
+        }
+    }
+}
+
+impl Format {
+    /// Bytes written as `input`.
+    pub fn parse(self, input: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Hex => parse_hex(text(input)?),
+            Format::Base64 => parse_base64(text(input)?),
+            Format::Raw => Ok(input.to_vec()),
+            Format::RedisCli => parse_redis_cli(input),
+        }
+    }
+
+    /// `b` written in this format.
+    pub fn write(self, b: &[u8]) -> Vec<u8> {
+        match self {
+            Format::Hex => (hex(b) + "\n").into_bytes(),
+            Format::Base64 => (base64(b) + "\n").into_bytes(),
+            Format::Raw => b.to_vec(),
+            Format::RedisCli => (quote(b) + "\n").into_bytes(),
+        }
+    }
+}
+
+fn text(input: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(input).map_err(|_| "input is not text".to_string())
+}
+
+pub fn hex(b: &[u8]) -> String {
+    b.iter()
+        .fold(String::with_capacity(b.len() * 2), |mut s, b| {
+            let _ = write!(s, "{:02x}", b);
+            s
+        })
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    let s = s.trim();
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    let digits = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16).ok_or(format!("invalid hex digit `{}`", c)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if digits.len() % 2 != 0 {
+        return Err("odd number of hex digits".into());
+    }
+    Ok(digits.chunks(2).map(|d| (d[0] * 16 + d[1]) as u8).collect())
+}
+
+fn base64(b: &[u8]) -> String {
+    let mut s = String::with_capacity(b.len().div_ceil(3) * 4);
+    for chunk in b.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+fn parse_base64(s: &str) -> Result<Vec<u8>, String> {
+    let sextets = s
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| {
+            BASE64
+                .iter()
+                .position(|&b| b as char == c)
+                .ok_or(format!("invalid base64 character `{}`", c))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if sextets.len() % 4 == 1 {
+        return Err("truncated base64".into());
+    }
+    let mut b = Vec::with_capacity(sextets.len() * 3 / 4);
+    for chunk in sextets.chunks(4) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &s)| n | (s as u32) << (18 - 6 * i));
+        b.extend_from_slice(&n.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(b)
+}
+
+/// `b` quoted the way `redis-cli` prints strings.
+fn quote(b: &[u8]) -> String {
+    let mut s = String::from("\"");
+    for &b in b {
+        match b {
+            b'"' | b'\\' => {
+                s.push('\\');
+                s.push(b as char);
+            }
+            b'\n' => s.push_str("\\n"),
+            b'\r' => s.push_str("\\r"),
+            b'\t' => s.push_str("\\t"),
+            0x07 => s.push_str("\\a"),
+            0x08 => s.push_str("\\b"),
+            0x20..=0x7e => s.push(b as char),
+            _ => {
+                let _ = write!(s, "\\x{:02x}", b);
+            }
+        }
+    }
+    s.push('"');
+    s
+}
+
+fn parse_redis_cli(input: &[u8]) -> Result<Vec<u8>, String> {
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
+    match input
+        .strip_prefix(b"\"")
+        .and_then(|s| s.strip_suffix(b"\""))
+    {
+        Some(quoted) => unquote(quoted),
+        None => Ok(input.to_vec()),
+    }
+}
+
+fn unquote(s: &[u8]) -> Result<Vec<u8>, String> {
+    let mut b = Vec::with_capacity(s.len());
+    let mut s = s.iter().copied();
+    while let Some(c) = s.next() {
+        if c != b'\\' {
+            b.push(c);
+            continue;
+        }
+        b.push(match s.next() {
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'a') => 0x07,
+            Some(b'b') => 0x08,
+            Some(b'x') => {
+                let digits = [s.next(), s.next()];
+                let digits = digits
+                    .iter()
+                    .map(|d| d.and_then(|d| (d as char).to_digit(16)))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or("invalid `\\x` escape")?;
+                (digits[0] * 16 + digits[1]) as u8
+            }
+            Some(c) => c,
+            None => return Err("unterminated escape".into()),
+        });
+    }
+    Ok(b)
+}
@@ -0,0 +1,267 @@
+/*!
+Command line tool to look into values stored with `ya-redis-derive`.
+
+```text
+ya-inspect decode  --schema user.json --format hex dump.txt
+redis-cli GET user:1 | ya-inspect explain --schema user.json --format redis-cli
+ya-inspect encode  --schema user.json --format base64 user.json
+```
+
+The schema file is the JSON form of a [`ValueSchema`], as written by
+`serde_json::to_string(&User::value_schema())`.
+ */
+mod format;
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::File,
+    io::{self, Read, Write},
+    process::exit,
+};
+
+use ya_binary_format::{
+    schema::ValueSchema,
+    value::{Explained, Span, Value},
+};
+
+use crate::format::{hex, Format};
+
+const USAGE: &str = "\
+usage: ya-inspect <command> --schema <file> [--format <format>] [<input>]
+
+commands:
+    decode     print the value as JSON
+    explain    print what each byte of the value was read as
+    stats      print how many bytes each part of the value takes
+    encode     encode a JSON value
+
+formats: hex (default), base64, raw, redis-cli
+The input is read from stdin unless a file is given.
+";
+
+/// Bytes shown per line by `explain`.
+const BYTES_PER_LINE: usize = 8;
+
+#[derive(Clone, Copy)]
+enum Command {
+    Decode,
+    Explain,
+    Stats,
+    Encode,
+}
+
+struct Args {
+    command: Command,
+    schema: String,
+    format: Format,
+    input: Option<String>,
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprint!("ya-inspect: {}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+    if let Err(e) = run(&args) {
+        eprintln!("ya-inspect: {}", e);
+        exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("decode") => Command::Decode,
+        Some("explain") => Command::Explain,
+        Some("stats") => Command::Stats,
+        Some("encode") => Command::Encode,
+        Some(c) => return Err(format!("unknown command `{}`", c)),
+        None => return Err("missing command".into()),
+    };
+    let mut schema = None;
+    let mut format = Format::Hex;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => schema = Some(args.next().ok_or("missing schema file")?),
+            "--format" => format = args.next().ok_or("missing format")?.parse()?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+    Ok(Args {
+        command,
+        schema: schema.ok_or("missing `--schema`")?,
+        format,
+        input,
+    })
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let schema = read(Some(&args.schema))?;
+    let schema: ValueSchema = serde_json::from_slice(&schema)
+        .map_err(|e| format!("invalid schema {}: {}", args.schema, e))?;
+    let input = read(args.input.as_deref())?;
+    let output = match args.command {
+        Command::Encode => {
+            let value: Value =
+                serde_json::from_slice(&input).map_err(|e| format!("invalid JSON: {}", e))?;
+            let b = schema.encode(&value).map_err(|e| e.to_string())?;
+            args.format.write(&b)
+        }
+        Command::Decode => {
+            let b = args.format.parse(&input)?;
+            let value = schema.decode(&b).map_err(|e| e.to_string())?;
+            let mut json = serde_json::to_vec_pretty(&value).map_err(|e| e.to_string())?;
+            json.push(b'\n');
+            json
+        }
+        Command::Explain => {
+            let b = args.format.parse(&input)?;
+            let explained = schema.explain(&b).map_err(|e| e.to_string())?;
+            explain(b.len(), &explained).into_bytes()
+        }
+        Command::Stats => {
+            let b = args.format.parse(&input)?;
+            let explained = schema.explain(&b).map_err(|e| e.to_string())?;
+            stats(b.len(), &explained).into_bytes()
+        }
+    };
+    io::stdout()
+        .write_all(&output)
+        .map_err(|e| format!("cannot write output: {}", e))
+}
+
+/// Contents of the file at `path`, or of stdin.
+fn read(path: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut b = Vec::new();
+    match path {
+        Some(path) => File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut b))
+            .map_err(|e| format!("cannot read {}: {}", path, e))?,
+        None => io::stdin()
+            .read_to_end(&mut b)
+            .map_err(|e| format!("cannot read stdin: {}", e))?,
+    };
+    Ok(b)
+}
+
+fn explain(input_len: usize, explained: &Explained) -> String {
+    let mut out = format!(
+        "input {} bytes, payload {} bytes\n\n{:>6}  {:<width$}  {:<24}  what\n",
+        input_len,
+        explained.payload.len(),
+        "offset",
+        "bytes",
+        "path",
+        width = BYTES_PER_LINE * 3 - 1,
+    );
+    for span in &explained.spans {
+        let bytes = &explained.payload[span.start..span.end];
+        let mut lines = bytes.chunks(BYTES_PER_LINE);
+        let first = lines.next().unwrap_or(&[]);
+        out += &format!(
+            "{:>6}  {:<width$}  {:<24}  {}\n",
+            span.start,
+            spaced_hex(first),
+            path(span),
+            span.what,
+            width = BYTES_PER_LINE * 3 - 1,
+        );
+        for (i, line) in lines.enumerate() {
+            out += &format!(
+                "{:>6}  {}\n",
+                span.start + (i + 1) * BYTES_PER_LINE,
+                spaced_hex(line),
+            );
+        }
+    }
+    out
+}
+
+fn stats(input_len: usize, explained: &Explained) -> String {
+    let mut kinds = BTreeMap::<&str, usize>::new();
+    let mut paths = BTreeMap::<String, (usize, usize)>::new();
+    for span in &explained.spans {
+        let kind = match span.what.split(' ').next().unwrap_or("") {
+            "length" => "length prefixes",
+            "some" | "none" => "option tags",
+            "variant" => "variant indexes",
+            "flags" => "flag bitmaps",
+            "encrypted" => "encrypted fields",
+            _ => "values",
+        };
+        *kinds.entry(kind).or_default() += span.end - span.start;
+        let (bytes, count) = paths.entry(item_path(&span.path)).or_default();
+        *bytes += span.end - span.start;
+        *count += 1;
+    }
+    let payload = explained.payload.len();
+    let mut out = format!(
+        "input     {:>8} bytes\npayload   {:>8} bytes\noverhead  {:>8} bytes\n\n",
+        input_len,
+        payload,
+        input_len as isize - payload as isize,
+    );
+    for (kind, bytes) in &kinds {
+        out += &format!(
+            "{:<18}{:>8} bytes {:>5.1}%\n",
+            kind,
+            bytes,
+            percent(*bytes, payload)
+        );
+    }
+    out += &format!("\n{:<32}{:>8}  {:>6}\n", "path", "bytes", "reads");
+    let mut paths = paths.into_iter().collect::<Vec<_>>();
+    paths.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(&b.0)));
+    for (path, (bytes, count)) in paths {
+        out += &format!("{:<32}{:>8}  {:>6}\n", format!("${}", path), bytes, count);
+    }
+    out
+}
+
+fn path(span: &Span) -> String {
+    format!("${}", span.path)
+}
+
+/// `path` with indices dropped, so all items of a sequence count together.
+fn item_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                out.push_str("[]");
+            }
+            ']' => in_index = false,
+            _ if !in_index => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn spaced_hex(b: &[u8]) -> String {
+    let hex = hex(b);
+    let mut out = String::with_capacity(b.len() * 3);
+    for (i, pair) in hex.as_bytes().chunks(2).enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        out.push_str(std::str::from_utf8(pair).unwrap());
+    }
+    out
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
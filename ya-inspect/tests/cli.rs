@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+use serde::Serialize;
+use ya_binary_format::{
+    schema::{Field, Fields, OptionsSchema, Schema, ValueSchema, Variant},
+    Options,
+};
+
+#[derive(Serialize)]
+struct User {
+    id: i64,
+    name: String,
+    email: Option<String>,
+    kinds: Vec<Kind>,
+}
+
+#[derive(Serialize)]
+enum Kind {
+    A,
+    B(u16),
+}
+
+fn user() -> User {
+    User {
+        id: 7,
+        name: "abc".into(),
+        email: None,
+        kinds: vec![Kind::A, Kind::B(300)],
+    }
+}
+
+fn schema_file() -> PathBuf {
+    let schema = ValueSchema {
+        options: OptionsSchema {
+            checksum: true,
+            ..OptionsSchema::default()
+        },
+        schema: Schema::Struct {
+            name: "User".into(),
+            fields: Fields::Named(vec![
+                Field::new("id", Schema::I64),
+                Field::new("name", Schema::String),
+                Field::new(
+                    "email",
+                    Schema::Option {
+                        item: Box::new(Schema::String),
+                    },
+                ),
+                Field::new(
+                    "kinds",
+                    Schema::Seq {
+                        item: Box::new(Schema::Enum {
+                            name: "Kind".into(),
+                            variants: vec![
+                                Variant::new("A", Fields::Unit),
+                                Variant::new("B", Fields::Newtype(Box::new(Schema::U16))),
+                            ],
+                        }),
+                    },
+                ),
+            ]),
+        },
+    };
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("user-schema.json");
+    fs::write(&path, serde_json::to_vec(&schema).unwrap()).unwrap();
+    path
+}
+
+fn encoded() -> Vec<u8> {
+    Options::new().checksum(true).to_bytes(&user())
+}
+
+fn inspect(args: &[&str], input: &[u8]) -> Output {
+    let schema = schema_file();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ya-inspect"))
+        .args(args)
+        .arg("--schema")
+        .arg(&schema)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout_bytes(output: Output) -> Vec<u8> {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
+
+fn stdout(output: Output) -> String {
+    String::from_utf8(stdout_bytes(output)).unwrap()
+}
+
+fn hex(b: &[u8]) -> String {
+    b.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn decode() {
+    let expected = serde_json::json!({
+        "id": 7,
+        "name": "abc",
+        "email": null,
+        "kinds": ["A", {"B": 300}],
+    });
+    let hex_input = hex(&encoded()) + "\n";
+    for (format, input) in [
+        ("hex", hex_input.into_bytes()),
+        ("raw", encoded()),
+        ("redis-cli", [encoded(), b"\n".to_vec()].concat()),
+    ] {
+        let json = stdout(inspect(&["decode", "--format", format], &input));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            expected,
+            "{}",
+            format
+        );
+    }
+}
+
+#[test]
+fn encode_base64_and_back() {
+    let json = br#"{"id": 7, "name": "abc", "kinds": ["A", {"B": 300}]}"#;
+    let base64 = stdout(inspect(&["encode", "--format", "base64"], json));
+    let raw = stdout_bytes(inspect(&["encode", "--format", "raw"], json));
+    assert_eq!(raw, encoded());
+    let quoted = stdout(inspect(&["encode", "--format", "redis-cli"], json));
+    assert!(quoted.starts_with("\"\\a\\x00"), "{}", quoted);
+    for (format, input) in [("base64", base64), ("redis-cli", quoted)] {
+        let json = stdout(inspect(&["decode", "--format", format], input.as_bytes()));
+        assert!(json.contains("\"B\": 300"), "{}", json);
+    }
+}
+
+#[test]
+fn explain() {
+    let out = stdout(inspect(&["explain", "--format", "raw"], &encoded()));
+    let lines = out
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>());
+    let lines = lines.collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        ["input", "22", "bytes,", "payload", "18", "bytes"],
+        "{}",
+        out
+    );
+    for expected in [
+        &[
+            "0", "07", "00", "00", "00", "00", "00", "00", "00", "$.id", "i64", "7",
+        ][..],
+        &["8", "03", "61", "62", "63", "$.name", "string", "\"abc\""],
+        &["12", "30", "$.email", "none"],
+        &["13", "02", "$.kinds", "length", "2"],
+        &["14", "00", "$.kinds[0]", "variant", "0", "(A)"],
+        &["15", "01", "$.kinds[1]", "variant", "1", "(B)"],
+        &["16", "2c", "01", "$.kinds[1].B", "u16", "300"],
+    ] {
+        assert!(
+            lines.iter().any(|l| l == expected),
+            "{:?}\n{}",
+            expected,
+            out
+        );
+    }
+}
+
+#[test]
+fn stats() {
+    let out = stdout(inspect(&["stats", "--format", "raw"], &encoded()));
+    assert!(out.contains("option tags"), "{}", out);
+    let line = out.lines().find(|l| l.starts_with("$.kinds[]")).unwrap();
+    assert_eq!(
+        line.split_whitespace().collect::<Vec<_>>(),
+        ["$.kinds[]", "2", "2"]
+    );
+}
+
+#[test]
+fn errors() {
+    let output = inspect(&["decode"], b"zz");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid hex digit"));
+    let output = inspect(&["decode", "--format", "raw"], &encoded()[1..]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("checksum"));
+    let output = inspect(&["unknown"], b"");
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,67 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Index, Member, Result};
+
+use crate::attrs::Container;
+
+/// `#[redis(args)]`: the fields written as command arguments in order.
+pub fn derive_args(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(Error::new(type_ident.span(), "`args` requires a struct")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`args` is not supported on generic types",
+        ));
+    }
+
+    let mut writes = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        };
+        let flag = container.arg_flags.iter().find(|(f, _)| *f == i);
+        let token = container.arg_tokens.iter().find(|(f, _)| *f == i);
+        writes.push(match (flag, token) {
+            (Some((_, flag)), None) => quote! {
+                if self.#member {
+                    out.write_arg(#flag.as_bytes());
+                }
+            },
+            (None, Some((_, token))) => quote! {
+                let args = ::redis::ToRedisArgs::to_redis_args(&self.#member);
+                if !args.is_empty() {
+                    out.write_arg(#token.as_bytes());
+                    for arg in args {
+                        out.write_arg(&arg);
+                    }
+                }
+            },
+            (None, None) => quote! {
+                ::redis::ToRedisArgs::write_redis_args(&self.#member, out);
+            },
+            (Some((_, flag)), Some(_)) => {
+                return Err(Error::new_spanned(
+                    flag,
+                    "a field is either a `flag` or has a `token`",
+                ))
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl ::redis::ToRedisArgs for #type_ident {
+            fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                #(#writes)*
+            }
+
+            fn is_single_arg(&self) -> bool {
+                false
+            }
+        }
+    })
+}
@@ -0,0 +1,88 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ext::IdentExt, Data, DeriveInput, Error, Fields, Result};
+
+use crate::{attrs::Container, rename::RenameRule};
+
+/// `#[redis(as_str)]`: a unit-only enum stored as the name of its variant.
+pub fn derive_as_str(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let variants = match &input.data {
+        Data::Enum(data)
+            if data
+                .variants
+                .iter()
+                .all(|v| matches!(v.fields, Fields::Unit)) =>
+        {
+            &data.variants
+        }
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`as_str` requires an enum with only unit variants",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`as_str` is not supported on generic types",
+        ));
+    }
+    let rename = RenameRule::from_attr(container.rename_all.as_ref())?;
+
+    let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+    let names = idents
+        .iter()
+        .map(|ident| rename.apply_to_variant(&ident.unraw().to_string()))
+        .collect::<Vec<_>>();
+    let type_name = type_ident.unraw().to_string();
+    let vis = &input.vis;
+
+    Ok(quote! {
+        impl #type_ident {
+            /// Name of the variant, as stored.
+            #vis fn as_str(&self) -> &'static str {
+                match self {
+                    #(Self::#idents => #names,)*
+                }
+            }
+        }
+
+        impl ::redis::ToRedisArgs for #type_ident {
+            fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                out.write_arg(self.as_str().as_bytes());
+            }
+        }
+
+        impl ::redis::FromRedisValue for #type_ident {
+            fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                let name: &[u8] = match v {
+                    ::redis::Value::Data(b) => b,
+                    ::redis::Value::Status(s) => s.as_bytes(),
+                    _ => {
+                        return Err(::redis::RedisError::from((
+                            ::redis::ErrorKind::TypeError,
+                            "the data got from redis was not a string",
+                        )))
+                    }
+                };
+                #(
+                    if name == #names.as_bytes() {
+                        return Ok(Self::#idents);
+                    }
+                )*
+                Err(::redis::RedisError::from((
+                    ::redis::ErrorKind::TypeError,
+                    "unknown variant name got from redis",
+                    format!(
+                        "{:?} is not a variant of {}, expected one of {}",
+                        ::std::string::String::from_utf8_lossy(name),
+                        #type_name,
+                        [#(#names),*].join(", "),
+                    ),
+                )))
+            }
+        }
+    })
+}
@@ -0,0 +1,410 @@
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Attribute, Data, DeriveInput, Error, Ident, Lit, LitInt, LitStr, Path, Result, Token,
+};
+
+use crate::serde_attrs;
+
+/// How the type is stored, given by at most one of the attributes naming a mode.
+#[derive(Default)]
+pub enum Mode {
+    /// A single value in the ya binary format or another codec.
+    #[default]
+    Value,
+    Hash,
+    Indexed,
+    AsStr,
+    Transparent,
+    /// `reply = "array"` or `"map"`
+    Reply(LitStr),
+    Args,
+}
+
+impl Mode {
+    /// The mode named by `item`, if it names one.
+    fn from_item(item: &AttrItem) -> Result<Option<Mode>> {
+        let flag = |mode| item.flag().map(|_| Some(mode));
+        match item.name.to_string().as_str() {
+            "hash" => flag(Mode::Hash),
+            "indexed" => flag(Mode::Indexed),
+            "as_str" => flag(Mode::AsStr),
+            "transparent" => flag(Mode::Transparent),
+            "reply" => Ok(Some(Mode::Reply(item.str()?))),
+            "args" => flag(Mode::Args),
+            _ => Ok(None),
+        }
+    }
+
+    /// The attribute naming the mode.
+    fn name(&self) -> &'static str {
+        match self {
+            Mode::Value => "value",
+            Mode::Hash => "hash",
+            Mode::Indexed => "indexed",
+            Mode::AsStr => "as_str",
+            Mode::Transparent => "transparent",
+            Mode::Reply(_) => "reply",
+            Mode::Args => "args",
+        }
+    }
+
+    /// Whether this is `reply = "map"`, the only reply whose items have names to rename.
+    fn is_reply_map(&self) -> bool {
+        matches!(self, Mode::Reply(reply) if reply.value() == "map")
+    }
+}
+
+/// Options given by `#[redis(...)]` on the type and its fields.
+#[derive(Default)]
+pub struct Container {
+    pub mode: Mode,
+    pub dictionary: bool,
+    pub packed_flags: bool,
+    pub checksum: bool,
+    pub compress: bool,
+    pub compress_above: Option<LitInt>,
+    pub encrypt: bool,
+    pub key_provider: Option<Path>,
+    /// Names of the encrypted fields and of the struct in serde
+    pub encrypted_fields: Vec<String>,
+    pub serde_name: String,
+    pub flattened_fields: Vec<Ident>,
+    pub counter_fields: Vec<Ident>,
+    pub view: bool,
+    pub fixed_layout: bool,
+    pub renamed_fields: Vec<(Ident, LitStr)>,
+    pub key: Option<LitStr>,
+    pub key_escape: bool,
+    /// `flag` and `token` of fields by position
+    pub arg_flags: Vec<(usize, LitStr)>,
+    pub arg_tokens: Vec<(usize, LitStr)>,
+    pub rename_all: Option<LitStr>,
+    pub codec: Option<Path>,
+    pub fallback: Option<Path>,
+}
+
+impl Container {
+    pub fn from_input(input: &DeriveInput) -> Result<Container> {
+        let mut container = Container::default();
+        let mut mode_item = None;
+        for item in parse_redis_attrs(&input.attrs)? {
+            if let Some(mode) = Mode::from_item(&item)? {
+                if let Some(first) = &mode_item {
+                    return Err(Error::new(
+                        item.name.span(),
+                        format!("`{}` cannot be combined with `{}`", item.name, first),
+                    ));
+                }
+                mode_item = Some(item.name.clone());
+                container.mode = mode;
+                continue;
+            }
+            match item.name.to_string().as_str() {
+                "dictionary" => container.dictionary = item.flag()?,
+                "packed_flags" => container.packed_flags = item.flag()?,
+                "checksum" => container.checksum = item.flag()?,
+                "compress" => container.compress = item.flag()?,
+                "compress_above" => container.compress_above = Some(item.int()?),
+                "encrypt" => container.encrypt = item.flag()?,
+                "key_provider" => container.key_provider = Some(item.path()?),
+                "view" => container.view = item.flag()?,
+                "fixed_layout" => container.fixed_layout = item.flag()?,
+                "key" => container.key = Some(item.str()?),
+                "key_escape" => container.key_escape = item.flag()?,
+                "rename_all" => container.rename_all = Some(item.str()?),
+                "codec" => container.codec = Some(item.path()?),
+                "fallback" => container.fallback = Some(item.path()?),
+                _ => return Err(item.unknown()),
+            }
+        }
+        for (i, field) in fields(&input.data).into_iter().enumerate() {
+            for item in parse_redis_attrs(&field.attrs)? {
+                match (item.name.to_string().as_str(), &field.ident) {
+                    ("flag", _) if matches!(container.mode, Mode::Args) => {
+                        container.arg_flags.push((i, item.str()?))
+                    }
+                    ("token", _) if matches!(container.mode, Mode::Args) => {
+                        container.arg_tokens.push((i, item.str()?))
+                    }
+                    ("flag" | "token", _) => {
+                        return Err(Error::new(
+                            item.name.span(),
+                            format!("`{}` on fields requires `#[redis(args)]`", item.name),
+                        ))
+                    }
+                    ("encrypt", Some(_)) if matches!(input.data, Data::Struct(_)) => {
+                        item.flag()?;
+                        let rename_all = serde_attrs::rename_all(input)?;
+                        container
+                            .encrypted_fields
+                            .push(serde_attrs::field_name(field, &rename_all)?);
+                    }
+                    ("flatten", Some(ident)) if matches!(container.mode, Mode::Hash) => {
+                        item.flag()?;
+                        container.flattened_fields.push(ident.clone());
+                    }
+                    ("counter", Some(ident)) if matches!(container.mode, Mode::Hash) => {
+                        item.flag()?;
+                        container.counter_fields.push(ident.clone());
+                    }
+                    ("rename", Some(ident)) if container.mode.is_reply_map() => {
+                        container.renamed_fields.push((ident.clone(), item.str()?));
+                    }
+                    ("rename", _) => {
+                        return Err(Error::new(
+                            item.name.span(),
+                            "`rename` on fields requires `#[redis(reply = \"map\")]`",
+                        ))
+                    }
+                    ("flatten" | "counter", _) => {
+                        return Err(Error::new(
+                            item.name.span(),
+                            format!("`{}` on fields requires `#[redis(hash)]`", item.name),
+                        ))
+                    }
+                    ("encrypt", _) => {
+                        return Err(Error::new(
+                            item.name.span(),
+                            "`encrypt` on fields requires a struct with named fields",
+                        ))
+                    }
+                    _ => return Err(item.unknown()),
+                }
+            }
+        }
+        if !container.encrypted_fields.is_empty() {
+            container.serde_name = serde_attrs::type_name(input)?;
+            // serde writes these as maps or as the inner value, not as structs with their fields
+            let unsupported = serde_attrs::serde_meta(&input.attrs)
+                .into_iter()
+                .find(|meta| meta.path().is_ident("transparent"))
+                .or_else(|| {
+                    fields(&input.data)
+                        .into_iter()
+                        .flat_map(|field| serde_attrs::serde_meta(&field.attrs))
+                        .find(|meta| meta.path().is_ident("flatten"))
+                });
+            if let Some(meta) = unsupported {
+                return Err(Error::new_spanned(
+                    meta,
+                    "`encrypt` on fields cannot be combined with this serde attribute",
+                ));
+            }
+        }
+        container.check_mode(input)?;
+        if let Some(codec) = &container.codec {
+            let format_options = [
+                (container.dictionary, "dictionary"),
+                (container.packed_flags, "packed_flags"),
+                (container.checksum, "checksum"),
+                (
+                    container.compress || container.compress_above.is_some(),
+                    "compress",
+                ),
+                (
+                    container.encrypt || !container.encrypted_fields.is_empty(),
+                    "encrypt",
+                ),
+                (container.view, "view"),
+                (container.fixed_layout, "fixed_layout"),
+            ];
+            if let Some((_, option)) = format_options.iter().find(|(set, _)| *set) {
+                return Err(Error::new_spanned(
+                    codec,
+                    format!(
+                        "`codec` replaces the ya binary format, so it cannot be combined with `{}`",
+                        option
+                    ),
+                ));
+            }
+        }
+        if let Some(fallback) = &container.fallback {
+            // `checksum` keeps legacy values from decoding in the current format, so that must be
+            // the ya binary format
+            let conflicts = [
+                (container.codec.is_some(), "codec"),
+                (container.view, "view"),
+                (container.fixed_layout, "fixed_layout"),
+            ];
+            if let Some((_, option)) = conflicts.iter().find(|(set, _)| *set) {
+                return Err(Error::new_spanned(
+                    fallback,
+                    format!("`fallback` cannot be combined with `{}`", option),
+                ));
+            }
+            if !container.checksum {
+                return Err(Error::new_spanned(
+                    fallback,
+                    "`fallback` requires `checksum`, which keeps legacy values from decoding as binary",
+                ));
+            }
+        }
+        if let (Some(rename_all), false) = (
+            &container.rename_all,
+            matches!(container.mode, Mode::AsStr) || container.mode.is_reply_map(),
+        ) {
+            return Err(Error::new_spanned(
+                rename_all,
+                "`rename_all` requires `#[redis(as_str)]` or `#[redis(reply = \"map\")]`",
+            ));
+        }
+        if (container.encrypt || !container.encrypted_fields.is_empty())
+            && container.key_provider.is_none()
+        {
+            return Err(Error::new(
+                input.ident.span(),
+                "`encrypt` requires `#[redis(key_provider = path::to::KEYS)]`",
+            ));
+        }
+        if container.key_escape && container.key.is_none() {
+            return Err(Error::new(
+                input.ident.span(),
+                "`key_escape` requires `#[redis(key = \"...\")]`",
+            ));
+        }
+        Ok(container)
+    }
+
+    /// Reject the options which do not apply to values stored the way `mode` does.
+    fn check_mode(&self, input: &DeriveInput) -> Result<()> {
+        let layouts = [
+            (self.view, "`view`"),
+            (self.fixed_layout, "`fixed_layout`"),
+            (!self.encrypted_fields.is_empty(), "`encrypt` on fields"),
+        ];
+        let codecs = [
+            (self.codec.is_some(), "`codec`"),
+            (self.fallback.is_some(), "`fallback`"),
+        ];
+        let format_options = [
+            (self.dictionary, "`dictionary`"),
+            (self.packed_flags, "`packed_flags`"),
+            (self.checksum, "`checksum`"),
+            (self.compress || self.compress_above.is_some(), "`compress`"),
+            (self.encrypt, "`encrypt`"),
+        ];
+        let conflicts = match self.mode {
+            Mode::Value => Vec::new(),
+            // the fields or items are encoded on their own, with the format options of the type
+            Mode::Hash | Mode::Indexed => [&layouts[..], &codecs[..]].concat(),
+            // plain Redis values, or no value at all
+            Mode::AsStr | Mode::Transparent | Mode::Reply(_) | Mode::Args => {
+                [&format_options[..], &layouts[..], &codecs[..]].concat()
+            }
+        };
+        match conflicts.iter().find(|(set, _)| *set) {
+            Some((_, option)) => Err(Error::new(
+                input.ident.span(),
+                format!("`{}` cannot be combined with {}", self.mode.name(), option),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// All fields of the struct, or of all variants of the enum.
+fn fields(data: &Data) -> Vec<&syn::Field> {
+    match data {
+        Data::Struct(data) => data.fields.iter().collect(),
+        Data::Enum(data) => data.variants.iter().flat_map(|v| &v.fields).collect(),
+        Data::Union(data) => data.fields.named.iter().collect(),
+    }
+}
+
+/// A single `name` or `name = value` item in `#[redis(...)]`.
+pub struct AttrItem {
+    pub name: Ident,
+    pub value: Option<AttrValue>,
+}
+
+pub enum AttrValue {
+    Lit(Lit),
+    Path(Path),
+}
+
+impl Parse for AttrItem {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.call(Ident::parse_any)?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            if input.peek(Lit) {
+                Some(AttrValue::Lit(input.parse()?))
+            } else {
+                Some(AttrValue::Path(input.parse()?))
+            }
+        } else {
+            None
+        };
+        Ok(AttrItem { name, value })
+    }
+}
+
+impl AttrItem {
+    pub fn flag(&self) -> Result<bool> {
+        match self.value {
+            None => Ok(true),
+            Some(_) => Err(Error::new(
+                self.name.span(),
+                format!("`{}` does not take a value", self.name),
+            )),
+        }
+    }
+
+    pub fn int(&self) -> Result<LitInt> {
+        match &self.value {
+            Some(AttrValue::Lit(Lit::Int(lit))) => Ok(lit.clone()),
+            _ => Err(Error::new(
+                self.name.span(),
+                format!("`{}` takes an integer like `{} = 1`", self.name, self.name),
+            )),
+        }
+    }
+
+    pub fn str(&self) -> Result<LitStr> {
+        match &self.value {
+            Some(AttrValue::Lit(Lit::Str(lit))) => Ok(lit.clone()),
+            _ => Err(Error::new(
+                self.name.span(),
+                format!(
+                    "`{}` takes a string like `{} = \"...\"`",
+                    self.name, self.name
+                ),
+            )),
+        }
+    }
+
+    /// A path given as is or as a string literal like serde.
+    pub fn path(&self) -> Result<Path> {
+        match &self.value {
+            Some(AttrValue::Path(path)) => Ok(path.clone()),
+            Some(AttrValue::Lit(Lit::Str(lit))) => lit.parse(),
+            _ => Err(Error::new(
+                self.name.span(),
+                format!(
+                    "`{}` takes a path like `{} = path::to::item`",
+                    self.name, self.name
+                ),
+            )),
+        }
+    }
+
+    pub fn unknown(&self) -> Error {
+        Error::new(
+            self.name.span(),
+            format!("unknown redis attribute `{}`", self.name),
+        )
+    }
+}
+
+pub fn parse_redis_attrs(attrs: &[Attribute]) -> Result<Vec<AttrItem>> {
+    let mut items = Vec::new();
+    for attr in attrs {
+        if attr.path.is_ident("redis") {
+            items
+                .extend(attr.parse_args_with(Punctuated::<AttrItem, Token![,]>::parse_terminated)?);
+        }
+    }
+    Ok(items)
+}
@@ -0,0 +1,148 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Data, DeriveInput, Error, Fields, Result};
+
+use crate::{
+    attrs::Container,
+    serde_attrs::{reject_unknown, serde_meta},
+};
+
+/// `#[redis(fixed_layout)]`: `FixedSize`, field offsets and `GETRANGE`/`SETRANGE` accessors.
+pub fn derive_fixed_layout(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    type_ident.span(),
+                    "`fixed_layout` requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`fixed_layout` requires a struct with named fields",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`fixed_layout` is not supported on generic types",
+        ));
+    }
+    for (set, option) in [
+        (container.packed_flags, "packed_flags"),
+        (container.checksum, "checksum"),
+        (
+            container.compress || container.compress_above.is_some(),
+            "compress",
+        ),
+        (
+            container.encrypt || !container.encrypted_fields.is_empty(),
+            "encrypt",
+        ),
+    ] {
+        if set {
+            return Err(Error::new(
+                type_ident.span(),
+                format!("`fixed_layout` cannot be combined with `{}`", option),
+            ));
+        }
+    }
+    reject_unknown(
+        &input.attrs,
+        &[
+            "rename",
+            "rename_all",
+            "bound",
+            "crate",
+            "deny_unknown_fields",
+            "expecting",
+        ],
+        "`fixed_layout`",
+    )?;
+
+    let mut sizes = Vec::new();
+    let mut items = Vec::new();
+    let mut offset = quote! { 0 };
+    for field in fields {
+        // anything else, like `with` or `skip_serializing_if`, may change the size of the field
+        reject_unknown(&field.attrs, &["skip"], "`fixed_layout`")?;
+        let skipped = serde_meta(&field.attrs)
+            .iter()
+            .any(|meta| meta.path().is_ident("skip"));
+        if skipped {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let name = ident.unraw().to_string();
+        let vis = &field.vis;
+        let ty = &field.ty;
+        let size = quote! { <#ty as ::ya_redis_derive::FixedSize>::SIZE };
+        let offset_ident = format_ident!("{}_OFFSET", name.to_uppercase());
+        let get_ident = format_ident!("get_{}", name);
+        let set_ident = format_ident!("set_{}", name);
+        let offset_doc = format!("Byte offset of `{}` in the encoded value.", name);
+        let get_doc = format!("Read `{}` of the value at `key` with `GETRANGE`.", name);
+        let set_doc = format!(
+            "Overwrite `{}` of the value at `key` with `SETRANGE`, leaving the other fields as they are.",
+            name
+        );
+        items.push(quote! {
+            #[doc = #offset_doc]
+            #vis const #offset_ident: usize = #offset;
+
+            #[doc = #get_doc]
+            #vis fn #get_ident<C, K>(con: &mut C, key: K) -> ::redis::RedisResult<#ty>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                let b: ::std::vec::Vec<u8> = if #size == 0 {
+                    ::std::vec::Vec::new()
+                } else {
+                    ::redis::cmd("GETRANGE")
+                        .arg(key)
+                        .arg(Self::#offset_ident)
+                        .arg(Self::#offset_ident + #size - 1)
+                        .query(con)?
+                };
+                ::ya_redis_derive::from_bytes(&b).map_err(|e| {
+                    ::redis::RedisError::from((
+                        ::redis::ErrorKind::TypeError,
+                        "failed to decode the data got from redis",
+                        e.to_string(),
+                    ))
+                })
+            }
+
+            #[doc = #set_doc]
+            #vis fn #set_ident<C, K>(con: &mut C, key: K, value: &#ty) -> ::redis::RedisResult<()>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                ::redis::cmd("SETRANGE")
+                    .arg(key)
+                    .arg(Self::#offset_ident)
+                    .arg(::ya_redis_derive::to_bytes(value))
+                    .query(con)
+            }
+        });
+        offset = quote! { Self::#offset_ident + #size };
+        sizes.push(size);
+    }
+
+    Ok(quote! {
+        impl ::ya_redis_derive::FixedSize for #type_ident {
+            const SIZE: usize = 0 #(+ #sizes)*;
+        }
+
+        impl #type_ident {
+            #(#items)*
+        }
+    })
+}
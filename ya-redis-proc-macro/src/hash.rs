@@ -0,0 +1,202 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Data, DeriveInput, Error, Fields, Meta, Result, Type};
+
+use crate::{attrs::Container, impls::options, serde_attrs::serde_meta};
+
+/// `#[redis(hash)]`: a hash field per struct field, written for `HSET` and read from `HGETALL`.
+pub fn derive_hash(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    type_ident.span(),
+                    "`hash` requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`hash` requires a struct with named fields",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`hash` is not supported on generic types",
+        ));
+    }
+
+    let vis = &input.vis;
+    let options = options(container);
+    let mut writes = Vec::new();
+    let mut reads = Vec::new();
+    let mut accessors = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let counter = container.counter_fields.contains(ident);
+        let mut skipped = false;
+        for meta in serde_meta(&field.attrs) {
+            match meta {
+                Meta::Path(path) if path.is_ident("skip") => skipped = true,
+                Meta::NameValue(nv)
+                    if nv.path.is_ident("with")
+                        || nv.path.is_ident("serialize_with")
+                        || nv.path.is_ident("deserialize_with") =>
+                {
+                    return Err(Error::new_spanned(
+                        nv,
+                        "`hash` fields are encoded on their own, without `with`",
+                    ))
+                }
+                _ => {}
+            }
+        }
+        if skipped && counter {
+            return Err(Error::new(
+                ident.span(),
+                "`counter` fields cannot be skipped",
+            ));
+        }
+        if skipped {
+            reads.push(quote! { #ident: ::std::default::Default::default() });
+            continue;
+        }
+        let name = ident.unraw().to_string();
+        if container.flattened_fields.contains(ident) {
+            if counter {
+                return Err(Error::new(
+                    ident.span(),
+                    "`counter` fields cannot be flattened",
+                ));
+            }
+            writes.push(quote! {
+                ::ya_redis_derive::hash::write_flattened(out, #name, &self.#ident);
+            });
+            reads.push(quote! {
+                #ident: ::ya_redis_derive::hash::read_flattened(&fields, #name)?
+            });
+            continue;
+        }
+        writes.push(quote! {
+            ::ya_redis_derive::hash::write_field(out, &options, #name, &self.#ident);
+        });
+        reads.push(quote! {
+            #ident: ::ya_redis_derive::hash::read_field(&fields, &options, #name)?
+        });
+
+        let vis = &field.vis;
+        let ty = &field.ty;
+        let get_ident = format_ident!("get_{}", name);
+        let set_ident = format_ident!("set_{}", name);
+        let get_doc = format!("Read `{}` of the hash at `key` with `HGET`.", name);
+        let set_doc = format!(
+            "Write `{}` of the hash at `key` with `HSET`, leaving the other fields as they are.",
+            name
+        );
+        accessors.push(quote! {
+            #[doc = #get_doc]
+            #vis fn #get_ident<C, K>(con: &mut C, key: K) -> ::redis::RedisResult<#ty>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                ::ya_redis_derive::hash::get_field(con, key, &#options, #name)
+            }
+
+            #[doc = #set_doc]
+            #vis fn #set_ident<C, K>(con: &mut C, key: K, value: &#ty) -> ::redis::RedisResult<()>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                ::ya_redis_derive::hash::set_field(con, key, &#options, #name, value)
+            }
+        });
+        if counter {
+            let (incr, command) = match number_kind(ty) {
+                Some(Number::Integer) => (quote! { incr_field }, "HINCRBY"),
+                Some(Number::Float) => (quote! { incr_float_field }, "HINCRBYFLOAT"),
+                None => return Err(Error::new_spanned(
+                    ty,
+                    "`counter` requires a float, or an integer which fits in the `i64` of `HINCRBY`",
+                )),
+            };
+            let incr_ident = format_ident!("incr_{}", name);
+            let incr_doc = format!(
+                "Add `delta` to `{}` of the hash at `key` with `{}`, and return the new value.",
+                name, command
+            );
+            accessors.push(quote! {
+                #[doc = #incr_doc]
+                #vis fn #incr_ident<C, K>(con: &mut C, key: K, delta: #ty) -> ::redis::RedisResult<#ty>
+                where
+                    C: ::redis::ConnectionLike,
+                    K: ::redis::ToRedisArgs,
+                {
+                    ::ya_redis_derive::hash::#incr(con, key, #name, delta)
+                }
+            });
+        }
+    }
+
+    Ok(quote! {
+        impl ::redis::ToRedisArgs for #type_ident {
+            fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                let options = #options;
+                #(#writes)*
+            }
+
+            fn is_single_arg(&self) -> bool {
+                false
+            }
+        }
+
+        impl ::redis::FromRedisValue for #type_ident {
+            fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                let fields: ::std::collections::HashMap<::std::vec::Vec<u8>, ::std::vec::Vec<u8>> =
+                    ::redis::FromRedisValue::from_redis_value(v)?;
+                let options = #options;
+                Ok(Self {
+                    #(#reads,)*
+                })
+            }
+        }
+
+        impl #type_ident {
+            /// Replace the hash at `key` with the fields of `self`, with `DEL` and `HSET` in a
+            /// `MULTI`. See [`ya_redis_derive::hash::save`].
+            #vis fn save<C, K>(&self, con: &mut C, key: K) -> ::redis::RedisResult<()>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                ::ya_redis_derive::hash::save(con, key, self)
+            }
+
+            #(#accessors)*
+        }
+    })
+}
+
+enum Number {
+    Integer,
+    Float,
+}
+
+/// Whether `ty` is a primitive integer which fits in an `i64`, which `HINCRBY` takes, or a float.
+fn number_kind(ty: &Type) -> Option<Number> {
+    let ident = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident()?.to_string(),
+        _ => return None,
+    };
+    match ident.as_str() {
+        "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" => Some(Number::Integer),
+        "f32" | "f64" => Some(Number::Float),
+        _ => None,
+    }
+}
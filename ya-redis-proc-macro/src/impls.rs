@@ -1,20 +1,56 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{GenericParam, Generics, Ident, ImplGenerics, TypeGenerics, WhereClause};
+use syn::{GenericParam, Generics, Ident, ImplGenerics, TypeGenerics, Visibility, WhereClause};
 
-pub fn derive_redis(type_ident: Ident, type_generics: Generics) -> proc_macro::TokenStream {
+use crate::attrs::Container;
+
+pub fn derive_redis(
+    vis: Visibility,
+    type_ident: Ident,
+    type_generics: Generics,
+    container: Container,
+) -> proc_macro::TokenStream {
     let (ser_impl_g, ser_ty_g, ser_wc) = split_for_ser(&type_generics);
     let (de_impl_g, de_ty_g, de_wc) = split_for_de(&type_generics);
+    let codec = codec(&container);
+    let decode = match &container.fallback {
+        Some(fallback) => quote! {
+            ::ya_redis_derive::codec::decode_with_fallback(&#codec, &#fallback, v).map(|(v, _)| v)
+        },
+        None => quote! { ::ya_redis_derive::Codec::decode(&#codec, v) },
+    };
+    let migrating = container.fallback.as_ref().map(|fallback| {
+        quote! {
+            impl #de_impl_g #type_ident #de_ty_g #de_wc {
+                /// `GET` the value at `key`, rewriting it in the current format if it was stored
+                /// in the `fallback` one.
+                #vis fn get_migrating<C, K>(con: &mut C, key: K) -> ::redis::RedisResult<::std::option::Option<Self>>
+                where
+                    Self: ::serde::ser::Serialize,
+                    C: ::redis::ConnectionLike,
+                    K: ::redis::ToRedisArgs,
+                {
+                    ::ya_redis_derive::codec::get_migrating(con, key, &#codec, &#fallback)
+                }
+            }
+        }
+    });
     quote! (
         impl #ser_impl_g ::redis::ToRedisArgs for #type_ident #ser_ty_g #ser_wc {
             fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
-                out.write_arg(&::ya_redis_derive::to_bytes(self));
+                out.write_arg(&::ya_redis_derive::codec::encode_or_panic(&#codec, self));
             }
         }
         impl #de_impl_g ::redis::FromRedisValue for #type_ident #de_ty_g #de_wc {
             fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
                 match v {
-                    ::redis::Value::Data(v) => Ok(::ya_redis_derive::from_bytes(v)),
+                    ::redis::Value::Data(v) => #decode.map_err(|e| {
+                        ::redis::RedisError::from((
+                            ::redis::ErrorKind::TypeError,
+                            "failed to decode the data got from redis",
+                            e.to_string(),
+                        ))
+                    }),
                     _ => Err(::redis::RedisError::from((
                         ::redis::ErrorKind::TypeError,
                         "the data got from redis was not single binary data",
@@ -22,20 +58,58 @@ pub fn derive_redis(type_ident: Ident, type_generics: Generics) -> proc_macro::T
                 }
             }
         }
+        #migrating
     )
     .into()
 }
 
+/// The `Codec` values are stored with: the one given by `codec`, or the options.
+pub fn codec(container: &Container) -> TokenStream {
+    match &container.codec {
+        Some(codec) => quote! { #codec },
+        None => options(container),
+    }
+}
+
+pub fn options(container: &Container) -> TokenStream {
+    let mut options = quote! { ::ya_redis_derive::Options::new() };
+    if container.dictionary {
+        options.extend(quote! { .dictionary(true) });
+    }
+    if container.packed_flags {
+        options.extend(quote! { .packed_flags(true) });
+    }
+    if container.checksum {
+        options.extend(quote! { .checksum(true) });
+    }
+    if let Some(threshold) = &container.compress_above {
+        options.extend(quote! { .compress_above(#threshold) });
+    } else if container.compress {
+        options.extend(quote! { .compress(true) });
+    }
+    if let Some(provider) = &container.key_provider {
+        if container.encrypt {
+            options.extend(quote! { .encrypt_value(&#provider) });
+        }
+        if !container.encrypted_fields.is_empty() {
+            let name = &container.serde_name;
+            let fields = &container.encrypted_fields;
+            options.extend(quote! { .encrypt_fields(&#provider, #name, &[#(#fields),*]) });
+        }
+    }
+    options
+}
+
 /// From https://github.com/TeXitoi/structopt/blob/master/structopt-derive/src/lib.rs
 /// Thank you!
-struct TraitBoundAmendments {
-    tokens: TokenStream,
+pub struct TraitBoundAmendments {
+    pub tokens: TokenStream,
     need_where: bool,
     need_comma: bool,
 }
 
 impl TraitBoundAmendments {
-    fn new(where_clause: Option<&WhereClause>) -> Self {
+    pub fn new(where_clause: Option<&WhereClause>) -> Self {
         let tokens = TokenStream::new();
         let (need_where, need_comma) = if let Some(where_clause) = where_clause {
             if where_clause.predicates.trailing_punct() {
@@ -53,7 +127,7 @@ impl TraitBoundAmendments {
         }
     }
 
-    fn add(&mut self, amendment: TokenStream) {
+    pub fn add(&mut self, amendment: TokenStream) {
         if self.need_where {
             self.tokens.extend(quote! { where });
             self.need_where = false;
@@ -66,7 +140,7 @@ impl TraitBoundAmendments {
     }
 }
 
-fn split_for_ser(generics: &Generics) -> (ImplGenerics, TypeGenerics, TokenStream) {
+fn split_for_ser(generics: &Generics) -> (ImplGenerics<'_>, TypeGenerics<'_>, TokenStream) {
     let mut t = TraitBoundAmendments::new(generics.where_clause.as_ref());
     for param in &generics.params {
         if let GenericParam::Type(param) = param {
@@ -80,7 +154,7 @@ fn split_for_ser(generics: &Generics) -> (ImplGenerics, TypeGenerics, TokenStrea
     (impl_generics, ty_generics, where_clause)
 }
 
-fn split_for_de(generics: &Generics) -> (ImplGenerics, TypeGenerics, TokenStream) {
+fn split_for_de(generics: &Generics) -> (ImplGenerics<'_>, TypeGenerics<'_>, TokenStream) {
     let mut t = TraitBoundAmendments::new(generics.where_clause.as_ref());
     for param in &generics.params {
         if let GenericParam::Type(param) = param {
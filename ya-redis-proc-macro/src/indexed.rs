@@ -0,0 +1,102 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, GenericArgument, PathArguments, Result, Type};
+
+use crate::{attrs::Container, impls::options};
+
+/// `#[redis(indexed)]`: store a newtype over a `Vec` as an indexed sequence, with range reads.
+pub fn derive_indexed(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let item = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => vec_item(&fields.unnamed[0].ty),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| {
+        Error::new(
+            type_ident.span(),
+            "`indexed` requires a newtype struct over a `Vec`",
+        )
+    })?;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`indexed` is not supported on generic types",
+        ));
+    }
+    let vis = &input.vis;
+    let options = options(container);
+
+    Ok(quote! {
+        impl ::redis::ToRedisArgs for #type_ident {
+            fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                out.write_arg(&::ya_redis_derive::indexed::to_bytes(&#options, &self.0));
+            }
+        }
+
+        impl ::redis::FromRedisValue for #type_ident {
+            fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                match v {
+                    ::redis::Value::Data(v) => ::ya_redis_derive::indexed::from_bytes(&#options, v)
+                        .map(Self)
+                        .map_err(|e| {
+                            ::redis::RedisError::from((
+                                ::redis::ErrorKind::TypeError,
+                                "failed to decode the data got from redis",
+                                e.to_string(),
+                            ))
+                        }),
+                    _ => Err(::redis::RedisError::from((
+                        ::redis::ErrorKind::TypeError,
+                        "the data got from redis was not single binary data",
+                    ))),
+                }
+            }
+        }
+
+        impl #type_ident {
+            /// Number of items stored at `key`, read with a single `GETRANGE`.
+            #vis fn get_len<C, K>(con: &mut C, key: K) -> ::redis::RedisResult<usize>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                ::ya_redis_derive::indexed::get_len(con, key)
+            }
+
+            /// Items in `range` of the value at `key`, read without the others.
+            /// See [`ya_redis_derive::indexed::get_range`].
+            #vis fn get_range<C, K>(
+                con: &mut C,
+                key: K,
+                range: ::std::ops::Range<usize>,
+            ) -> ::redis::RedisResult<::std::vec::Vec<#item>>
+            where
+                C: ::redis::ConnectionLike,
+                K: ::redis::ToRedisArgs,
+            {
+                ::ya_redis_derive::indexed::get_range(con, key, &#options, range)
+            }
+        }
+    })
+}
+
+/// `T` of a `Vec<T>`.
+fn vec_item(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Vec" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(item) => Some(item),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,137 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Data, DeriveInput, Error, Fields, Ident, LitStr, Result};
+
+use crate::attrs::Container;
+
+/// `#[redis(key = "user:{id}")]`: `redis_key(&self)` and `key_for(id)` formatting the template.
+pub fn derive_key(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let template = container.key.as_ref().unwrap();
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    type_ident.span(),
+                    "`key` requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`key` requires a struct with named fields",
+            ))
+        }
+    };
+    let (format, names) = parse_template(template)?;
+    let mut params = Vec::new();
+    for name in &names {
+        let field = fields
+            .iter()
+            .find(|field| field.ident.as_ref().unwrap() == name)
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    template,
+                    format!("no field `{}` in `{}` for the key", name, type_ident),
+                )
+            })?;
+        if !params.iter().any(|(ident, _)| ident == name) {
+            params.push((field.ident.clone().unwrap(), &field.ty));
+        }
+    }
+    // with `key_escape`, escaped like the fields of `RedisKey`, so that a `:` in a value cannot
+    // forge another key
+    let args = names.iter().map(|name| {
+        if container.key_escape {
+            quote! { ::ya_redis_derive::key::escape(&::std::string::ToString::to_string(#name)) }
+        } else {
+            quote! { #name }
+        }
+    });
+    let param_idents = params.iter().map(|(ident, _)| ident).collect::<Vec<_>>();
+    let param_types = params.iter().map(|(_, ty)| ty);
+    // so that a field which cannot be formatted is reported at its type
+    let display_checks = params.iter().map(|(ident, ty)| {
+        quote_spanned! {ty.span()=>
+            let #ident: &dyn ::std::fmt::Display = #ident;
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let vis = &input.vis;
+    let key_doc = format!("Key of this value, `{}`.", template.value());
+    let key_for_doc = format!(
+        "Key of the value with these fields, `{}`.",
+        template.value()
+    );
+
+    Ok(quote! {
+        impl #impl_generics #type_ident #ty_generics #where_clause {
+            #[doc = #key_doc]
+            #vis fn redis_key(&self) -> ::std::string::String {
+                Self::key_for(#(&self.#param_idents),*)
+            }
+
+            #[doc = #key_for_doc]
+            #[allow(clippy::ptr_arg)]
+            #vis fn key_for(#(#param_idents: &#param_types),*) -> ::std::string::String {
+                #(#display_checks)*
+                ::std::format!(#format, #(#args),*)
+            }
+        }
+    })
+}
+
+/// The `format!` string of the template, and the fields in its placeholders in order.
+fn parse_template(template: &LitStr) -> Result<(LitStr, Vec<Ident>)> {
+    let value = template.value();
+    let mut format = String::new();
+    let mut names = Vec::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                format.push_str("{{");
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                format.push_str("}}");
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err(Error::new_spanned(
+                        template,
+                        "unclosed `{` in the key, write `{{` for a literal one",
+                    ));
+                }
+                let ident = syn::parse_str::<Ident>(&name).map_err(|_| {
+                    Error::new_spanned(
+                        template,
+                        format!("`{{{}}}` in the key is not a field name", name),
+                    )
+                })?;
+                format.push_str("{}");
+                names.push(ident);
+            }
+            '}' => {
+                return Err(Error::new_spanned(
+                    template,
+                    "unmatched `}` in the key, write `}}` for a literal one",
+                ))
+            }
+            c => format.push(c),
+        }
+    }
+    Ok((LitStr::new(&format, template.span()), names))
+}
@@ -1,12 +1,90 @@
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
+use attrs::Mode;
+
+mod args;
+mod as_str;
+mod attrs;
+mod fixed;
+mod hash;
 mod impls;
+mod indexed;
+mod key;
+mod redis_key;
+mod rename;
+mod reply;
+mod schema;
+mod serde_attrs;
+mod transparent;
+mod view;
 
-#[proc_macro_derive(Redis)]
+#[proc_macro_derive(Redis, attributes(redis))]
 pub fn derive_redis(tokenstream: TokenStream) -> TokenStream {
     let input = parse_macro_input!(tokenstream as DeriveInput);
-    let type_ident = input.ident;
-    let type_generics = input.generics;
-    impls::derive_redis(type_ident, type_generics)
+    let container = match attrs::Container::from_input(&input) {
+        Ok(container) => container,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let mut tokens = match &container.key {
+        Some(_) => match key::derive_key(&input, &container) {
+            Ok(key) => key,
+            Err(e) => return e.to_compile_error().into(),
+        },
+        None => proc_macro2::TokenStream::new(),
+    };
+    tokens.extend(derive_impls(input, container));
+    tokens.into()
+}
+
+/// The redis impls and helpers of the way the container attributes store the type.
+fn derive_impls(input: DeriveInput, container: attrs::Container) -> proc_macro2::TokenStream {
+    let result = match &container.mode {
+        Mode::Value => return derive_value(input, container),
+        Mode::Hash => hash::derive_hash(&input, &container),
+        Mode::Indexed => indexed::derive_indexed(&input, &container),
+        Mode::AsStr => as_str::derive_as_str(&input, &container),
+        Mode::Transparent => transparent::derive_transparent(&input),
+        Mode::Reply(reply) => reply::derive_reply(&input, &container, reply),
+        Mode::Args => args::derive_args(&input, &container),
+    };
+    result.unwrap_or_else(|e| e.to_compile_error())
+}
+
+/// A single value encoded with the ya binary format or another codec.
+fn derive_value(input: DeriveInput, container: attrs::Container) -> proc_macro2::TokenStream {
+    let mut extra = proc_macro2::TokenStream::new();
+    if container.view {
+        match view::derive_view(&input, &container) {
+            Ok(view) => extra.extend(view),
+            Err(e) => return e.to_compile_error(),
+        }
+    }
+    if container.fixed_layout {
+        match fixed::derive_fixed_layout(&input, &container) {
+            Ok(fixed) => extra.extend(fixed),
+            Err(e) => return e.to_compile_error(),
+        }
+    }
+    let mut tokens: proc_macro2::TokenStream =
+        impls::derive_redis(input.vis, input.ident, input.generics, container).into();
+    tokens.extend(extra);
+    tokens
+}
+
+#[proc_macro_derive(RedisSchema, attributes(redis))]
+pub fn derive_redis_schema(tokenstream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(tokenstream as DeriveInput);
+    attrs::Container::from_input(&input)
+        .and_then(|container| schema::derive_schema(&input, &container))
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(RedisKey, attributes(redis))]
+pub fn derive_redis_key(tokenstream: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(tokenstream as DeriveInput);
+    redis_key::derive_redis_key(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
 }
@@ -0,0 +1,136 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ext::IdentExt, Data, DeriveInput, Error, Index, LitStr, Result};
+
+use crate::{attrs::parse_redis_attrs, rename::RenameRule};
+
+/// `#[derive(RedisKey)]`: a struct formatted as its prefix and fields separated by `:`.
+pub fn derive_redis_key(input: &DeriveInput) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`RedisKey` requires a struct",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`RedisKey` is not supported on generic types",
+        ));
+    }
+    let mut prefix = None;
+    for item in parse_redis_attrs(&input.attrs)? {
+        match item.name.to_string().as_str() {
+            "prefix" => prefix = Some(item.str()?),
+            _ => return Err(item.unknown()),
+        }
+    }
+    for field in fields {
+        if let Some(item) = parse_redis_attrs(&field.attrs)?.first() {
+            return Err(item.unknown());
+        }
+    }
+    // `UserKey` is prefixed with `user` by default
+    let prefix = prefix.map(|lit| lit.value()).unwrap_or_else(|| {
+        let name = type_ident.unraw().to_string();
+        let name = match name.strip_suffix("Key") {
+            Some(stripped) if !stripped.is_empty() => stripped.to_owned(),
+            _ => name,
+        };
+        RenameRule::SnakeCase.apply_to_variant(&name)
+    });
+
+    let members = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = Index::from(i);
+                quote! { #index }
+            }
+        })
+        .collect::<Vec<_>>();
+    let names = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.unraw().to_string(),
+            None => i.to_string(),
+        });
+    let separators = (0..members.len()).map(|i| if i == 0 && prefix.is_empty() { "" } else { ":" });
+    let count = members.len();
+    let parsed = members
+        .iter()
+        .zip(names)
+        .enumerate()
+        .map(|(i, (member, name))| {
+            let message = format!("invalid `{}`", name);
+            quote! {
+                #member: fields[#i]
+                    .parse()
+                    .map_err(|_| ::ya_redis_derive::key::ParseKeyError::new(key, #message))?
+            }
+        });
+    let prefix = LitStr::new(&prefix, type_ident.span());
+
+    Ok(quote! {
+        impl ::std::fmt::Display for #type_ident {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(#prefix)?;
+                #(
+                    f.write_str(#separators)?;
+                    f.write_str(&::ya_redis_derive::key::escape(&self.#members.to_string()))?;
+                )*
+                Ok(())
+            }
+        }
+
+        impl ::std::str::FromStr for #type_ident {
+            type Err = ::ya_redis_derive::key::ParseKeyError;
+
+            #[allow(unused_variables)]
+            fn from_str(key: &str) -> ::std::result::Result<Self, Self::Err> {
+                let fields = ::ya_redis_derive::key::parse(key, #prefix, #count)?;
+                Ok(Self { #(#parsed,)* })
+            }
+        }
+
+        impl ::redis::ToRedisArgs for #type_ident {
+            fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                out.write_arg(self.to_string().as_bytes());
+            }
+        }
+
+        impl ::redis::FromRedisValue for #type_ident {
+            fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                let key = match v {
+                    ::redis::Value::Data(b) => ::std::str::from_utf8(b).map_err(|_| {
+                        ::redis::RedisError::from((
+                            ::redis::ErrorKind::TypeError,
+                            "the key got from redis was not utf-8",
+                        ))
+                    })?,
+                    ::redis::Value::Status(s) => s.as_str(),
+                    _ => {
+                        return Err(::redis::RedisError::from((
+                            ::redis::ErrorKind::TypeError,
+                            "the data got from redis was not a string",
+                        )))
+                    }
+                };
+                key.parse().map_err(|e: ::ya_redis_derive::key::ParseKeyError| {
+                    ::redis::RedisError::from((
+                        ::redis::ErrorKind::TypeError,
+                        "failed to parse the key got from redis",
+                        e.to_string(),
+                    ))
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ext::IdentExt, Data, DeriveInput, Error, Fields, Index, LitStr, Member, Result};
+
+use crate::{attrs::Container, rename::RenameRule};
+
+/// `#[redis(reply = "array")]` or `"map"`: read the struct from the items of an array reply,
+/// by position or by the names in its key/value pairs.
+pub fn derive_reply(
+    input: &DeriveInput,
+    container: &Container,
+    reply: &LitStr,
+) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let map = match reply.value().as_str() {
+        "array" => false,
+        "map" => true,
+        _ => {
+            return Err(Error::new_spanned(
+                reply,
+                "`reply` is either `\"array\"` or `\"map\"`",
+            ))
+        }
+    };
+    let fields = match &input.data {
+        Data::Struct(data) if !map || matches!(data.fields, Fields::Named(_)) => &data.fields,
+        _ if map => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`reply = \"map\"` requires a struct with named fields",
+            ))
+        }
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`reply = \"array\"` requires a struct",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`reply` is not supported on generic types",
+        ));
+    }
+    let rename = RenameRule::from_attr(container.rename_all.as_ref())?;
+
+    let mut members = Vec::new();
+    let mut reads = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let (member, name) = match &field.ident {
+            Some(ident) => {
+                let name = container
+                    .renamed_fields
+                    .iter()
+                    .find(|(renamed, _)| renamed == ident)
+                    .map(|(_, name)| name.value())
+                    .unwrap_or_else(|| rename.apply_to_field(&ident.unraw().to_string()));
+                (Member::Named(ident.clone()), name)
+            }
+            None => (Member::Unnamed(Index::from(i)), i.to_string()),
+        };
+        let item = if map {
+            quote! { entries.get(#name.as_bytes()).copied() }
+        } else {
+            quote! { items.get(#i) }
+        };
+        members.push(member);
+        reads.push(quote! { ::ya_redis_derive::reply::field(#item, #name)? });
+    }
+    let items = if map {
+        quote! { let entries = ::ya_redis_derive::reply::entries(v)?; }
+    } else {
+        quote! { let items = ::ya_redis_derive::reply::items(v)?; }
+    };
+
+    Ok(quote! {
+        impl ::redis::FromRedisValue for #type_ident {
+            fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                #items
+                Ok(Self {
+                    #(#members: #reads,)*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,189 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, GenericParam, Lit, Meta, Path, Result};
+
+use crate::{
+    attrs::{Container, Mode},
+    impls::{options, TraitBoundAmendments},
+    rename::RenameRule,
+    serde_attrs::{self, reject_unknown, serde_meta},
+};
+
+pub fn derive_schema(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let unsupported = match container.mode {
+        Mode::Indexed => Some("`RedisSchema` cannot describe `indexed` values"),
+        Mode::Hash => Some("`RedisSchema` cannot describe `hash` values"),
+        Mode::AsStr | Mode::Transparent => {
+            Some("`RedisSchema` cannot describe values stored as plain Redis strings")
+        }
+        Mode::Value | Mode::Reply(_) | Mode::Args => None,
+    };
+    if let Some(message) = unsupported {
+        return Err(Error::new(type_ident.span(), message));
+    }
+    reject_unknown(&input.attrs, CONTAINER_ATTRS, "`RedisSchema`")?;
+    let name = serde_attrs::type_name(input)?;
+    let rename_all = serde_attrs::rename_all(input)?;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = fields(&data.fields, &rename_all)?;
+            quote! {
+                ::ya_redis_derive::schema::Schema::Struct {
+                    name: #name.into(),
+                    fields: #fields,
+                }
+            }
+        }
+        Data::Enum(data) => {
+            let rename_all_fields = serde_attrs::rename_rule(&input.attrs, "rename_all_fields")?;
+            let variants = data
+                .variants
+                .iter()
+                .map(|v| {
+                    reject_unknown(&v.attrs, VARIANT_ATTRS, "`RedisSchema`")?;
+                    let name = serde_attrs::variant_name(v, &rename_all)?;
+                    let fields = match serde_attrs::rename_rule(&v.attrs, "rename_all")? {
+                        RenameRule::None => fields(&v.fields, &rename_all_fields)?,
+                        rule => fields(&v.fields, &rule)?,
+                    };
+                    Ok(quote! { ::ya_redis_derive::schema::Variant::new(#name, #fields) })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! {
+                ::ya_redis_derive::schema::Schema::Enum {
+                    name: #name.into(),
+                    variants: ::std::vec![#(#variants),*],
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(Error::new(
+                data.union_token.span,
+                "`RedisSchema` cannot be derived for unions",
+            ))
+        }
+    };
+    let options = options(container);
+
+    let mut t = TraitBoundAmendments::new(input.generics.where_clause.as_ref());
+    for param in &input.generics.params {
+        if let GenericParam::Type(param) = param {
+            let param_ident = &param.ident;
+            t.add(quote! { #param_ident : ::ya_redis_derive::RedisSchema });
+        }
+    }
+    let trait_bound_amendments = t.tokens;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::ya_redis_derive::RedisSchema for #type_ident #ty_generics #where_clause #trait_bound_amendments {
+            fn schema() -> ::ya_redis_derive::schema::Schema {
+                ::ya_redis_derive::schema::named::<Self>(#name, || #body)
+            }
+
+            fn value_schema() -> ::ya_redis_derive::schema::ValueSchema {
+                ::ya_redis_derive::schema::ValueSchema {
+                    options: (&#options).into(),
+                    schema: <Self as ::ya_redis_derive::RedisSchema>::schema(),
+                }
+            }
+        }
+    })
+}
+
+/// Serde attributes which do not change the encoded value, or which the schema accounts for.
+/// The others, like `skip_serializing_if`, `flatten` or `tag`, change it in ways it does not.
+const CONTAINER_ATTRS: &[&str] = &[
+    "rename",
+    "rename_all",
+    "rename_all_fields",
+    "bound",
+    "crate",
+    "default",
+    "deny_unknown_fields",
+    "expecting",
+];
+const VARIANT_ATTRS: &[&str] = &["rename", "rename_all", "alias", "bound"];
+const FIELD_ATTRS: &[&str] = &[
+    "rename", "skip", "with", "alias", "bound", "borrow", "default",
+];
+
+fn fields(fields: &Fields, rename_all: &RenameRule) -> Result<TokenStream> {
+    let mut schemas = Vec::new();
+    for field in fields {
+        if let Some(schema) = field_schema(field)? {
+            schemas.push((field, schema));
+        }
+    }
+    Ok(match fields {
+        Fields::Named(_) => {
+            let fields = schemas
+                .iter()
+                .map(|(f, schema)| {
+                    let name = serde_attrs::field_name(f, rename_all)?;
+                    Ok(quote! { ::ya_redis_derive::schema::Field::new(#name, #schema) })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! { ::ya_redis_derive::schema::Fields::Named(::std::vec![#(#fields),*]) }
+        }
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => match schemas.first() {
+            Some((_, schema)) => {
+                quote! { ::ya_redis_derive::schema::Fields::Newtype(::std::boxed::Box::new(#schema)) }
+            }
+            None => quote! { ::ya_redis_derive::schema::Fields::Unit },
+        },
+        Fields::Unnamed(_) => {
+            let schemas = schemas.iter().map(|(_, schema)| schema);
+            quote! { ::ya_redis_derive::schema::Fields::Tuple(::std::vec![#(#schemas),*]) }
+        }
+        Fields::Unit => quote! { ::ya_redis_derive::schema::Fields::Unit },
+    })
+}
+
+/// Schema of the field as serde writes it, or `None` if it is skipped.
+fn field_schema(field: &syn::Field) -> Result<Option<TokenStream>> {
+    reject_unknown(&field.attrs, FIELD_ATTRS, "`RedisSchema`")?;
+    let ty = &field.ty;
+    let schema = quote! { <#ty as ::ya_redis_derive::RedisSchema>::schema() };
+    for meta in serde_meta(&field.attrs) {
+        match meta {
+            Meta::Path(path) if path.is_ident("skip") => return Ok(None),
+            Meta::NameValue(nv) if nv.path.is_ident("with") => {
+                let encoding = match &nv.lit {
+                    Lit::Str(lit) => lit.parse::<Path>().ok().and_then(|p| encoding(&p)),
+                    _ => None,
+                };
+                let encoding = encoding.ok_or_else(|| {
+                    Error::new_spanned(
+                        &nv,
+                        "`RedisSchema` supports only the `with` modules of `encoding`",
+                    )
+                })?;
+                return Ok(Some(quote! {
+                    ::ya_redis_derive::schema::Schema::Encoded {
+                        encoding: ::ya_redis_derive::schema::Encoding::#encoding,
+                        schema: ::std::boxed::Box::new(#schema),
+                    }
+                }));
+            }
+            _ => {}
+        }
+    }
+    Ok(Some(schema))
+}
+
+/// Variant of `Encoding` for a `with` path into `ya_binary_format::encoding`.
+fn encoding(path: &Path) -> Option<TokenStream> {
+    let mut segments = path.segments.iter().rev();
+    let module = segments.next()?.ident.to_string();
+    if segments.next()?.ident != "encoding" {
+        return None;
+    }
+    match module.as_str() {
+        "delta" => Some(quote! { Delta }),
+        "delta_of_delta" => Some(quote! { DeltaOfDelta }),
+        "rle" => Some(quote! { Rle }),
+        _ => None,
+    }
+}
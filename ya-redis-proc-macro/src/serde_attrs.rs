@@ -0,0 +1,121 @@
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt, Attribute, DeriveInput, Error, Lit, LitStr, Meta, MetaList, NestedMeta, Result,
+    Variant,
+};
+
+use crate::rename::RenameRule;
+
+/// Items of the `#[serde(...)]` attributes. Malformed ones are left for serde to report.
+pub fn serde_meta(attrs: &[Attribute]) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(meta) => Some(meta),
+            NestedMeta::Lit(_) => None,
+        })
+        .collect()
+}
+
+/// The name serde gives the type in `serialize_struct` and `deserialize_struct`.
+pub fn type_name(input: &DeriveInput) -> Result<String> {
+    Ok(match renamed(&input.attrs, "rename")? {
+        Some(lit) => lit.value(),
+        None => input.ident.unraw().to_string(),
+    })
+}
+
+/// The `rename_all` rule of the type.
+pub fn rename_all(input: &DeriveInput) -> Result<RenameRule> {
+    rename_rule(&input.attrs, "rename_all")
+}
+
+/// The rule of `rename_all` or `rename_all_fields` in the attributes.
+pub fn rename_rule(attrs: &[Attribute], name: &str) -> Result<RenameRule> {
+    RenameRule::from_attr(renamed(attrs, name)?.as_ref())
+}
+
+/// The name serde gives the variant, renamed by `rename` or the `rename_all` rule of the enum.
+pub fn variant_name(variant: &Variant, rename_all: &RenameRule) -> Result<String> {
+    Ok(match renamed(&variant.attrs, "rename")? {
+        Some(lit) => lit.value(),
+        None => rename_all.apply_to_variant(&variant.ident.unraw().to_string()),
+    })
+}
+
+/// The name serde gives the field, renamed by `rename` or the `rename_all` rule of the type.
+pub fn field_name(field: &syn::Field, rename_all: &RenameRule) -> Result<String> {
+    Ok(match (renamed(&field.attrs, "rename")?, &field.ident) {
+        (Some(lit), _) => lit.value(),
+        (None, Some(ident)) => rename_all.apply_to_field(&ident.unraw().to_string()),
+        (None, None) => {
+            return Err(Error::new_spanned(
+                field,
+                "serde names only fields of structs with named fields",
+            ))
+        }
+    })
+}
+
+/// The value of `name = "..."`, or of `name(serialize = "...", deserialize = "...")` if both are
+/// the same. Names which differ between serializing and deserializing are rejected, as the names
+/// are matched when doing either.
+fn renamed(attrs: &[Attribute], name: &str) -> Result<Option<LitStr>> {
+    let mut renamed = None;
+    for meta in serde_meta(attrs) {
+        match meta {
+            Meta::NameValue(nv) if nv.path.is_ident(name) => {
+                if let Lit::Str(lit) = nv.lit {
+                    renamed = Some(lit);
+                }
+            }
+            Meta::List(list) if list.path.is_ident(name) => renamed = Some(same_names(&list)?),
+            _ => {}
+        }
+    }
+    Ok(renamed)
+}
+
+fn same_names(list: &MetaList) -> Result<LitStr> {
+    let side = |side: &str| {
+        list.nested.iter().find_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident(side) => match &nv.lit {
+                Lit::Str(lit) => Some(lit.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+    };
+    match (side("serialize"), side("deserialize")) {
+        (Some(ser), Some(de)) if ser.value() == de.value() => Ok(ser),
+        _ => Err(Error::new_spanned(
+            list,
+            "different `serialize` and `deserialize` names are not supported here",
+        )),
+    }
+}
+
+/// Reject the serde attributes other than `known`, whose effect on the encoded value `derive`
+/// does not account for.
+pub fn reject_unknown(attrs: &[Attribute], known: &[&str], derive: &str) -> Result<()> {
+    for meta in serde_meta(attrs) {
+        let path = meta.path();
+        if !known.iter().any(|known| path.is_ident(known)) {
+            return Err(Error::new_spanned(
+                path,
+                format!(
+                    "{} does not support `#[serde({})]`",
+                    derive,
+                    path.to_token_stream()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Index, Member, Result};
+
+/// `#[redis(transparent)]`: a newtype stored as its field, with the field's own redis impls.
+pub fn derive_transparent(input: &DeriveInput) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let field = match &input.data {
+        Data::Struct(data) if data.fields.len() == 1 => data.fields.iter().next().unwrap(),
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`transparent` requires a struct with a single field",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`transparent` is not supported on generic types",
+        ));
+    }
+    let ty = &field.ty;
+    let (member, construct) = match &field.ident {
+        Some(ident) => (
+            Member::Named(ident.clone()),
+            quote! { |v| Self { #ident: v } },
+        ),
+        None => (Member::Unnamed(Index::from(0)), quote! { Self }),
+    };
+
+    Ok(quote! {
+        impl ::redis::ToRedisArgs for #type_ident {
+            fn write_redis_args<W : ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+                ::redis::ToRedisArgs::write_redis_args(&self.#member, out)
+            }
+
+            fn is_single_arg(&self) -> bool {
+                ::redis::ToRedisArgs::is_single_arg(&self.#member)
+            }
+        }
+
+        impl ::redis::FromRedisValue for #type_ident {
+            fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                <#ty as ::redis::FromRedisValue>::from_redis_value(v).map(#construct)
+            }
+        }
+    })
+}
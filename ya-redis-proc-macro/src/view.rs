@@ -0,0 +1,154 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Data, DeriveInput, Error, Fields, Lit, Meta, Path, Result};
+
+use crate::{
+    attrs::Container,
+    impls::options,
+    serde_attrs::{self, serde_meta},
+};
+
+/// `#[redis(view)]`: a `NameView<'a>` over the encoded bytes with an accessor per field.
+pub fn derive_view(input: &DeriveInput, container: &Container) -> Result<TokenStream> {
+    let type_ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    type_ident.span(),
+                    "`view` requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                type_ident.span(),
+                "`view` requires a struct with named fields",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            type_ident.span(),
+            "`view` is not supported on generic types",
+        ));
+    }
+    let vis = &input.vis;
+    let view_ident = format_ident!("{}View", type_ident);
+    let options = options(container);
+    let rename_all = serde_attrs::rename_all(input)?;
+    let mut accessors = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        // the name in the schema
+        let name = serde_attrs::field_name(field, &rename_all)?;
+        let vis = &field.vis;
+        let ty = &field.ty;
+        let doc = format!("Decode `{}`.", ident);
+        let decode = match deserializer(field) {
+            Some(Deserializer::Default) => {
+                quote! { <#ty as ::serde::Deserialize>::deserialize(de) }
+            }
+            Some(Deserializer::With(path)) => quote! { #path::deserialize(de) },
+            Some(Deserializer::Function(path)) => quote! { #path(de) },
+            None => continue,
+        };
+        if ident.unraw() == "new" {
+            return Err(Error::new(
+                ident.span(),
+                "`view` names its accessors after the fields, and `new` constructs the view",
+            ));
+        }
+        accessors.push(quote! {
+            #[doc = #doc]
+            #vis fn #ident(&self) -> ::std::result::Result<#ty, ::ya_redis_derive::Error> {
+                self.0.field_with(schema(), #name, |de| #decode)
+            }
+        });
+    }
+    let doc = format!(
+        "Encoded [`{}`] whose fields are decoded on demand.",
+        type_ident
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone)]
+        #vis struct #view_ident<'a>(::ya_redis_derive::view::View<'a>);
+
+        const _: () = {
+            fn schema() -> &'static ::ya_redis_derive::schema::Schema {
+                static SCHEMA: ::std::sync::OnceLock<::ya_redis_derive::schema::Schema> =
+                    ::std::sync::OnceLock::new();
+                SCHEMA.get_or_init(<#type_ident as ::ya_redis_derive::RedisSchema>::schema)
+            }
+
+            impl<'a> #view_ident<'a> {
+                /// View the encoded value `b`, which is verified, decrypted and decompressed as needed.
+                #vis fn new(b: &'a [u8]) -> ::std::result::Result<Self, ::ya_redis_derive::Error> {
+                    ::ya_redis_derive::view::View::new(&#options, b).map(Self)
+                }
+
+                #(#accessors)*
+            }
+
+            impl ::redis::FromRedisValue for #view_ident<'static> {
+                fn from_redis_value(v: &::redis::Value) -> ::redis::RedisResult<Self> {
+                    match v {
+                        ::redis::Value::Data(v) => {
+                            ::ya_redis_derive::view::View::from_vec(&#options, v.clone())
+                                .map(Self)
+                                .map_err(|e| {
+                                    ::redis::RedisError::from((
+                                        ::redis::ErrorKind::TypeError,
+                                        "failed to decode the data got from redis",
+                                        e.to_string(),
+                                    ))
+                                })
+                        }
+                        _ => Err(::redis::RedisError::from((
+                            ::redis::ErrorKind::TypeError,
+                            "the data got from redis was not single binary data",
+                        ))),
+                    }
+                }
+            }
+        };
+    })
+}
+
+enum Deserializer {
+    Default,
+    /// `#[serde(with = "module")]`
+    With(Path),
+    /// `#[serde(deserialize_with = "function")]`
+    Function(Path),
+}
+
+/// How serde decodes the field, or `None` if it is skipped.
+fn deserializer(field: &syn::Field) -> Option<Deserializer> {
+    let mut deserializer = Deserializer::Default;
+    for meta in serde_meta(&field.attrs) {
+        match meta {
+            Meta::Path(path) if path.is_ident("skip") => return None,
+            Meta::NameValue(nv) => {
+                let path = match &nv.lit {
+                    Lit::Str(lit) => lit.parse::<Path>().ok(),
+                    _ => None,
+                };
+                match path {
+                    Some(path) if nv.path.is_ident("with") => {
+                        deserializer = Deserializer::With(path)
+                    }
+                    Some(path) if nv.path.is_ident("deserialize_with") => {
+                        deserializer = Deserializer::Function(path)
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(deserializer)
+}